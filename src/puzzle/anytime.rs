@@ -0,0 +1,262 @@
+/*
+ * anytime.rs
+ * Anytime solver for interactive hints
+ * answers at once with the reduction solution, then keeps shortening it
+ *   - cut loops where the path revisits a board
+ *   - replace windows of the path by optimal detours (IDA* between two boards)
+ * until the options stop it, publishing every improvement
+ */
+
+use std::collections::HashMap;
+
+use crate::puzzle::ida::Outcome;
+use crate::puzzle::*;
+
+const FIRST_WINDOW: usize = 8;
+const WINDOW_STEP: usize = 4;
+
+impl Puzzle {
+    // Best solution found before the options stop it, every improvement goes to on_improve
+    // the first answer is the reduction solution, produced whatever the options say
+    pub fn solve_anytime(
+        &self,
+        opts: &mut SolveOptions,
+        mut on_improve: impl FnMut(&[Move]),
+    ) -> Result<Vec<Move>, SolveError> {
+        let mut best = self.clone().solve()?;
+        on_improve(&best);
+
+        let shorter = self.cut_loops(&best);
+        if shorter.len() < best.len() {
+            best = shorter;
+            on_improve(&best);
+        }
+        let mut nodes = 0;
+        let mut window = FIRST_WINDOW;
+        while window <= best.len() {
+            let mut improved = false;
+            let mut start = 0;
+            while start + window <= best.len() {
+                if opts.out_of_time() {
+                    return Ok(best);
+                }
+                let from = self.after(&best[..start]);
+                let to = self.after(&best[..start + window]);
+                let mut search = Detour::new(from, &to.grid, opts, &mut nodes, best.len());
+                match search.run(window as u32 - 1) {
+                    Err(_) => return Ok(best),
+                    Ok(Some(detour)) => {
+                        best.splice(start..start + window, detour);
+                        best = self.cut_loops(&best);
+                        on_improve(&best);
+                        improved = true;
+                    }
+                    Ok(None) => start += window / 2,
+                }
+            }
+            if !improved {
+                window += WINDOW_STEP;
+            }
+        }
+        Ok(best)
+    }
+
+    // board after playing moves (which are known to be legal)
+    fn after(&self, moves: &[Move]) -> Puzzle {
+        let mut p = self.clone();
+        for &dir in moves {
+            let _ = p.perform_move(dir);
+        }
+        p
+    }
+
+    // drop every stretch of the path that comes back to an earlier board
+    fn cut_loops(&self, moves: &[Move]) -> Vec<Move> {
+        let mut p = self.clone();
+        let mut path: Vec<Move> = Vec::new();
+        let mut seen = HashMap::from([(p.grid.clone(), 0)]);
+        for &dir in moves {
+            let _ = p.perform_move(dir);
+            match seen.get(&p.grid) {
+                Some(&len) => {
+                    path.truncate(len);
+                    seen.retain(|_, &mut at| at <= len);
+                }
+                None => {
+                    path.push(dir);
+                    seen.insert(p.grid.clone(), path.len());
+                }
+            }
+        }
+        path
+    }
+}
+
+/* IDA* towards an arbitrary board, Manhattan distance to the target positions */
+struct Detour<'a, 'o> {
+    puzzle: Puzzle,
+    home: Vec<usize>, // target cell of each tile
+    path: Vec<Move>,
+    opts: &'a mut SolveOptions<'o>,
+    nodes: &'a mut u64, // shared by every detour of one anytime solve
+    best: u32,          // reported as the bound
+}
+
+impl<'a, 'o> Detour<'a, 'o> {
+    fn new(
+        puzzle: Puzzle,
+        target: &[u32],
+        opts: &'a mut SolveOptions<'o>,
+        nodes: &'a mut u64,
+        best: usize,
+    ) -> Self {
+        let mut home = vec![0; target.len()];
+        for (idx, &tile) in target.iter().enumerate() {
+            if tile != WALL {
+                home[tile as usize] = idx;
+            }
+        }
+        Self {
+            puzzle,
+            home,
+            path: Vec::new(),
+            opts,
+            nodes,
+            best: best as u32,
+        }
+    }
+
+    // shortest path to the target board of at most `limit` moves
+    fn run(&mut self, limit: u32) -> Result<Option<Vec<Move>>, SolveError> {
+        let h = self.estimate();
+        let mut bound = h;
+        while bound <= limit {
+            match self.search(0, h, bound, None)? {
+                Outcome::Found => return Ok(Some(std::mem::take(&mut self.path))),
+                Outcome::Exceeded(next) => bound = next,
+            }
+        }
+        Ok(None)
+    }
+
+    // the short way round on a torus, or the bound would overshoot
+    fn distance(&self, idx: usize, tile: u32) -> u32 {
+        self.puzzle.cell_distance(idx, self.home[tile as usize])
+    }
+
+    fn estimate(&self) -> u32 {
+        let tiles = self.puzzle.grid.iter().enumerate();
+        tiles
+            .filter(|&(_, &tile)| tile != 0 && tile != WALL)
+            .map(|(idx, &tile)| self.distance(idx, tile))
+            .sum()
+    }
+
+    fn search(
+        &mut self,
+        g: u32,
+        h: u32,
+        bound: u32,
+        last: Option<Move>,
+    ) -> Result<Outcome, SolveError> {
+        *self.nodes += 1;
+        self.opts.tick(*self.nodes, Some(self.best))?;
+        if g + h > bound {
+            return Ok(Outcome::Exceeded(g + h));
+        }
+        if h == 0 {
+            return Ok(Outcome::Found);
+        }
+        let mut min = u32::MAX;
+        for dir in Move::ALL {
+            if Some(dir.inverse()) == last {
+                continue;
+            }
+            let (row, col) = self.puzzle.blank;
+            if self.puzzle.perform_move(dir).is_err() {
+                continue;
+            }
+            // the moved tile now sits where the blank was
+            let from = self.puzzle.index(self.puzzle.blank.0, self.puzzle.blank.1);
+            let to = self.puzzle.index(row, col);
+            let tile = self.puzzle.grid[to];
+            let next_h = h + self.distance(to, tile) - self.distance(from, tile);
+            self.path.push(dir);
+            match self.search(g + 1, next_h, bound, Some(dir))? {
+                Outcome::Found => return Ok(Outcome::Found),
+                Outcome::Exceeded(f) => min = min.min(f),
+            }
+            self.path.pop();
+            let _ = self.puzzle.perform_move(dir.inverse());
+        }
+        Ok(Outcome::Exceeded(min))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn solves(p: &Puzzle, moves: &[Move]) -> bool {
+        p.after(moves).is_solved()
+    }
+
+    #[test]
+    fn test_improves_until_deadline() {
+        let p = Puzzle::scrambled(4, 4, Scramble::Uniform, 9).unwrap();
+        let reduction = p.clone().solve().unwrap();
+        let mut published = Vec::new();
+        let mut opts = SolveOptions {
+            deadline: Some(Instant::now() + Duration::from_millis(300)),
+            ..SolveOptions::default()
+        };
+        let best = p
+            .solve_anytime(&mut opts, |moves| published.push(moves.len()))
+            .unwrap();
+        assert_eq!(published[0], reduction.len());
+        assert!(published.windows(2).all(|w| w[1] < w[0]));
+        assert_eq!(*published.last().unwrap(), best.len());
+        assert!(best.len() < reduction.len());
+        assert!(solves(&p, &best));
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let p = Puzzle::scrambled(5, 5, Scramble::Uniform, 1).unwrap();
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let mut opts = SolveOptions {
+            cancel: Some(cancel),
+            ..SolveOptions::default()
+        };
+        let best = p.solve_anytime(&mut opts, |_| {}).unwrap();
+        assert!(solves(&p, &best));
+        // a node budget ends the improvement loop too
+        let mut opts = SolveOptions {
+            max_nodes: Some(10_000),
+            ..SolveOptions::default()
+        };
+        let best = p.solve_anytime(&mut opts, |_| {}).unwrap();
+        assert!(solves(&p, &best));
+    }
+
+    #[test]
+    fn test_cut_loops() {
+        let p = Puzzle::goal(3, 3).unwrap();
+        // three turns of a 2x2 cycle bring every tile home
+        let cycle = [Move::Up, Move::Left, Move::Down, Move::Right];
+        let mut moves = cycle.repeat(3);
+        moves.extend([Move::Up, Move::Down]);
+        assert!(p.cut_loops(&moves).is_empty());
+        let (mut opts, mut nodes) = (SolveOptions::default(), 0);
+        let from = p.after(&[Move::Up, Move::Left]);
+        let mut search = Detour::new(from, &p.grid, &mut opts, &mut nodes, 2);
+        assert_eq!(search.run(4), Ok(Some(vec![Move::Right, Move::Down])));
+        // one wrap move away on a torus, not two
+        let p = p.with_topology(Topology::Torus);
+        let from = p.after(&[Move::Right]);
+        let mut search = Detour::new(from, &p.grid, &mut opts, &mut nodes, 1);
+        assert_eq!(search.run(1), Ok(Some(vec![Move::Left])));
+    }
+}
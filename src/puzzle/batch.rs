@@ -0,0 +1,121 @@
+/*
+ * batch.rs
+ * Solving many puzzles at once for content validation
+ * worker threads pull the next puzzle from a shared counter,
+ * results come back in input order with the time each solve took
+ */
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::puzzle::*;
+
+/* Which solver a batch runs on every puzzle */
+#[derive(Clone, Copy)]
+pub enum Solver<'a> {
+    Reduction,
+    Optimal,
+    Weighted(WeightedConfig),
+    Beam(BeamConfig, &'a (dyn Evaluator + Sync)),
+}
+
+impl Solver<'_> {
+    pub fn solve(&self, p: &Puzzle) -> Result<Vec<Move>, SolveError> {
+        match self {
+            Solver::Reduction => p.clone().solve(),
+            Solver::Optimal => p.solve_optimal(),
+            Solver::Weighted(config) => Ok(p.solve_weighted(config)?.moves),
+            Solver::Beam(config, evaluator) => Ok(p.solve_beam(config, *evaluator)?.moves),
+        }
+    }
+}
+
+/* Outcome of one puzzle in a batch */
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchResult {
+    pub result: Result<Vec<Move>, SolveError>,
+    pub elapsed: Duration,
+}
+
+// Solve every puzzle on `threads` workers (0 picks the number of cores)
+pub fn solve_batch(
+    puzzles: impl IntoIterator<Item = Puzzle>,
+    solver: &Solver,
+    threads: usize,
+) -> Vec<BatchResult> {
+    let puzzles: Vec<Puzzle> = puzzles.into_iter().collect();
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+    .min(puzzles.len())
+    .max(1);
+    let next = AtomicUsize::new(0);
+    let work = || {
+        let mut done = Vec::new();
+        loop {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            let Some(p) = puzzles.get(i) else {
+                return done;
+            };
+            let start = Instant::now();
+            let result = solver.solve(p);
+            done.push((
+                i,
+                BatchResult {
+                    result,
+                    elapsed: start.elapsed(),
+                },
+            ));
+        }
+    };
+    let mut results: Vec<Option<BatchResult>> = vec![None; puzzles.len()];
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads).map(|_| scope.spawn(work)).collect();
+        for worker in workers {
+            // a panicking solver is a bug, pass it on
+            let done = worker
+                .join()
+                .unwrap_or_else(|err| std::panic::resume_unwind(err));
+            for (i, result) in done {
+                results[i] = Some(result);
+            }
+        }
+    });
+    results.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_thread_safe_types() {
+        assert_send_sync::<Puzzle>();
+        assert_send_sync::<Vec<Move>>();
+        assert_send_sync::<SolveError>();
+        assert_send_sync::<BatchResult>();
+        assert_send_sync::<Solver>();
+    }
+
+    #[test]
+    fn test_results_in_input_order() {
+        let puzzles: Vec<Puzzle> = (0..40)
+            .map(|seed| Puzzle::scrambled(3, 3, Scramble::Uniform, seed).unwrap())
+            .chain([Puzzle::new(vec![vec![2, 1], vec![3, 0]]).unwrap()])
+            .collect();
+        let results = solve_batch(puzzles.clone(), &Solver::Optimal, 4);
+        assert_eq!(results.len(), puzzles.len());
+        for (p, batch) in puzzles.iter().zip(&results) {
+            assert_eq!(batch.result, p.solve_optimal());
+        }
+        assert_eq!(results[40].result, Err(SolveError::Unsolvable));
+        // more threads than puzzles, and the default thread count
+        let few = solve_batch(puzzles[..2].to_vec(), &Solver::Reduction, 8);
+        assert!(few.iter().all(|batch| batch.result.is_ok()));
+        assert!(solve_batch(Vec::new(), &Solver::Reduction, 0).is_empty());
+    }
+}
@@ -0,0 +1,487 @@
+/*
+ * blanks.rs
+ * Boards with k >= 1 blanks
+ * tiles 1..=cells - k in reading order, the blanks in the last k cells
+ * a move names the blank that moves, or the tile moving into a blank
+ * solvability
+ *   - one blank: the usual rule
+ *   - a line: tiles never pass each other, they must already be in order
+ *   - two or more blanks otherwise: a tile can move without the blank it
+ *     left coming back, so there is no parity left and all are solvable
+ * solving
+ *   - weighted A* on Manhattan distance, any blank may move
+ *   - if that gives up: the reduction solver on a one-blank board where the
+ *     extra blanks stand in for the missing tiles, with its moves from one
+ *     blank into another dropped
+ */
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::puzzle::search::{Open, ROOT, Tree};
+use crate::puzzle::*;
+
+/* A blank, named by its place in MultiBlank::blanks, moving one cell */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlankMove {
+    pub blank: usize,
+    pub dir: Move,
+}
+
+/* Board with any number of blanks */
+#[derive(Debug, Clone)]
+pub struct MultiBlank {
+    grid: Vec<u32>,              // 0 marks a blank
+    blanks: Vec<(usize, usize)>, // (row,col) of each blank, in reading order at the start
+    rows: usize,
+    cols: usize,
+}
+
+impl MultiBlank {
+    pub fn new(in_grid: Vec<Vec<u32>>) -> Result<Self, PuzzleError> {
+        let rows = in_grid.len();
+        let cols = in_grid.first().map_or(0, |row| row.len());
+        for (r, row) in in_grid.iter().enumerate() {
+            if row.len() != cols {
+                return Err(PuzzleError::RaggedRow {
+                    row: r,
+                    len: row.len(),
+                    expected: cols,
+                });
+            }
+        }
+        Self::from_tiles(rows, cols, in_grid.concat())
+    }
+
+    // Constructor from tiles in row-major order, every 0 a blank
+    pub fn from_tiles(rows: usize, cols: usize, tiles: Vec<u32>) -> Result<Self, PuzzleError> {
+        if rows == 0 || cols == 0 {
+            return Err(PuzzleError::EmptyBoard);
+        }
+        if tiles.len() != rows * cols {
+            return Err(PuzzleError::InvalidTiles);
+        }
+        let blanks: Vec<(usize, usize)> = (0..tiles.len())
+            .filter(|&i| tiles[i] == 0)
+            .map(|i| (i / cols, i % cols))
+            .collect();
+        if blanks.is_empty() {
+            return Err(PuzzleError::InvalidTiles);
+        }
+        // tiles must be exactly 1..=cells - k, each once
+        let count = tiles.len() - blanks.len();
+        let mut seen = vec![false; count];
+        for &tile in tiles.iter().filter(|&&t| t != 0) {
+            let goal = tile as usize - 1;
+            if goal >= count || seen[goal] {
+                return Err(PuzzleError::InvalidTiles);
+            }
+            seen[goal] = true;
+        }
+        Ok(Self {
+            grid: tiles,
+            blanks,
+            rows,
+            cols,
+        })
+    }
+
+    // Solved board with the given number of blanks
+    pub fn goal(rows: usize, cols: usize, blanks: usize) -> Result<Self, PuzzleError> {
+        let cells = rows * cols;
+        if blanks > cells {
+            return Err(PuzzleError::InvalidTiles);
+        }
+        let tiles = (1..=(cells - blanks) as u32)
+            .chain(std::iter::repeat_n(0, blanks))
+            .collect();
+        Self::from_tiles(rows, cols, tiles)
+    }
+
+    // Same flat, wall-free board with its highest blanks - 1 tiles lifted out
+    pub fn from_puzzle(p: &Puzzle, blanks: usize) -> Result<Self, PuzzleError> {
+        // walls would read as lifted tiles, and the moves here never wrap
+        if p.has_walls() || p.topology() != Topology::Flat {
+            return Err(PuzzleError::InvalidTiles);
+        }
+        let cells = p.rows() * p.cols();
+        if blanks == 0 || blanks > cells {
+            return Err(PuzzleError::InvalidTiles);
+        }
+        let kept = (cells - blanks) as u32;
+        let tiles = p
+            .tiles()
+            .iter()
+            .map(|&t| if t > kept { 0 } else { t })
+            .collect();
+        Self::from_tiles(p.rows(), p.cols(), tiles)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    // tiles in row-major order, 0 is a blank
+    pub fn tiles(&self) -> &[u32] {
+        &self.grid
+    }
+
+    // (row,col) of each blank, indexed as BlankMove::blank names them
+    pub fn blanks(&self) -> &[(usize, usize)] {
+        &self.blanks
+    }
+
+    // Check if the blank can move in mv.dir onto a tile
+    pub fn is_legal(&self, mv: BlankMove) -> bool {
+        self.blanks.get(mv.blank).is_some_and(|&cell| {
+            self.neighbour(cell, mv.dir)
+                .is_some_and(|(row, col)| self.grid[row * self.cols + col] != 0)
+        })
+    }
+
+    pub fn perform(&mut self, mv: BlankMove) -> Result<(), PuzzleError> {
+        if mv.blank >= self.blanks.len() {
+            return Err(PuzzleError::NoSuchBlank(mv.blank));
+        }
+        if !self.is_legal(mv) {
+            return Err(PuzzleError::IllegalMove(mv.dir));
+        }
+        let (row, col) = self.blanks[mv.blank];
+        let Some((to_row, to_col)) = self.neighbour((row, col), mv.dir) else {
+            return Err(PuzzleError::IllegalMove(mv.dir));
+        };
+        self.grid
+            .swap(row * self.cols + col, to_row * self.cols + to_col);
+        self.blanks[mv.blank] = (to_row, to_col);
+        Ok(())
+    }
+
+    // Slide a tile into the blank next to it in dir, as the blank's move
+    pub fn slide_tile(&mut self, tile: u32, dir: Move) -> Result<BlankMove, PuzzleError> {
+        let at = self.grid.iter().position(|&t| t == tile && t != 0);
+        let to = at.and_then(|idx| self.neighbour((idx / self.cols, idx % self.cols), dir));
+        let blank = to.and_then(|cell| self.blanks.iter().position(|&b| b == cell));
+        let Some(blank) = blank else {
+            return Err(PuzzleError::IllegalMove(dir));
+        };
+        let mv = BlankMove {
+            blank,
+            dir: dir.inverse(),
+        };
+        self.perform(mv)?;
+        Ok(mv)
+    }
+
+    // cell next to (row, col) in dir, if on the board
+    fn neighbour(&self, (row, col): (usize, usize), dir: Move) -> Option<(usize, usize)> {
+        match dir {
+            Move::Up if row > 0 => Some((row - 1, col)),
+            Move::Down if row + 1 < self.rows => Some((row + 1, col)),
+            Move::Left if col > 0 => Some((row, col - 1)),
+            Move::Right if col + 1 < self.cols => Some((row, col + 1)),
+            _ => None,
+        }
+    }
+
+    pub fn is_solved(&self) -> bool {
+        let count = self.grid.len() - self.blanks.len();
+        (0..self.grid.len()).all(|i| {
+            if i < count {
+                self.grid[i] as usize == i + 1
+            } else {
+                self.grid[i] == 0
+            }
+        })
+    }
+
+    pub fn is_solvable(&self) -> bool {
+        if self.blanks.len() == 1 {
+            return self.as_puzzle(0, &[]).is_some_and(|p| p.is_solvable());
+        }
+        if self.rows == 1 || self.cols == 1 {
+            let tiles = self.grid.iter().filter(|&&t| t != 0);
+            return tiles.zip(1..).all(|(&t, want)| t == want);
+        }
+        true
+    }
+
+    // sum over tiles of the grid distance to their goal cell
+    fn manhattan(&self) -> u32 {
+        let cols = self.cols;
+        (0..self.grid.len())
+            .filter(|&idx| self.grid[idx] != 0)
+            .map(|idx| {
+                let goal = self.grid[idx] as usize - 1;
+                ((idx / cols).abs_diff(goal / cols) + (idx % cols).abs_diff(goal % cols)) as u32
+            })
+            .sum()
+    }
+
+    // Solve with the default weighted search
+    pub fn solve(&self) -> Result<Vec<BlankMove>, SolveError> {
+        self.solve_with(&WeightedConfig::default(), &mut SolveOptions::default())
+    }
+
+    // Weighted A* with any blank moving, the reduction solver if it gives up
+    pub fn solve_with(
+        &self,
+        config: &WeightedConfig,
+        opts: &mut SolveOptions,
+    ) -> Result<Vec<BlankMove>, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        match self.search(config, opts) {
+            Err(SolveError::SearchExhausted) if self.rows > 1 && self.cols > 1 => {
+                self.solve_reduction()
+            }
+            result => result,
+        }
+    }
+
+    fn search(
+        &self,
+        config: &WeightedConfig,
+        opts: &mut SolveOptions,
+    ) -> Result<Vec<BlankMove>, SolveError> {
+        let weight = config.weight.max(1.0);
+        let mut tree = Tree(Vec::new());
+        let mut best_g = HashMap::from([(self.grid.clone(), 0)]);
+        let mut closed = HashSet::new();
+        let mut open = BinaryHeap::from([Open {
+            f: weight * self.manhattan() as f64,
+            g: 0,
+            node: ROOT,
+            puzzle: self.clone(),
+        }]);
+        let mut expanded = 0;
+        while let Some(Open {
+            g, node, puzzle, ..
+        }) = open.pop()
+        {
+            if puzzle.is_solved() {
+                return Ok(tree.path(node));
+            }
+            if !closed.insert(puzzle.grid.clone()) {
+                continue;
+            }
+            expanded += 1;
+            opts.tick(expanded, None)?;
+            for blank in 0..puzzle.blanks.len() {
+                for dir in Move::ALL {
+                    let mv = BlankMove { blank, dir };
+                    let mut child = puzzle.clone();
+                    if child.perform(mv).is_err() || closed.contains(&child.grid) {
+                        continue;
+                    }
+                    if best_g.get(&child.grid).is_some_and(|&best| best <= g + 1) {
+                        continue;
+                    }
+                    if best_g.len() >= config.max_states {
+                        return Err(SolveError::SearchExhausted);
+                    }
+                    best_g.insert(child.grid.clone(), g + 1);
+                    open.push(Open {
+                        f: (g + 1) as f64 + weight * child.manhattan() as f64,
+                        g: g + 1,
+                        node: tree.add(node, mv),
+                        puzzle: child,
+                    });
+                }
+            }
+        }
+        Err(SolveError::SearchExhausted)
+    }
+
+    // One-blank board with the missing tiles standing, in the given order, on
+    // every blank but the real one
+    fn as_puzzle(&self, real: usize, stand_ins: &[u32]) -> Option<Puzzle> {
+        let mut tiles = self.grid.clone();
+        let others = (0..self.blanks.len()).filter(|&b| b != real);
+        for (b, &tile) in others.zip(stand_ins) {
+            let (row, col) = self.blanks[b];
+            tiles[row * self.cols + col] = tile;
+        }
+        Puzzle::from_tiles(self.rows, self.cols, tiles).ok()
+    }
+
+    // The reduction solver with the extra blanks as stand-in tiles; swapping
+    // two stand-ins, or the real blank with the one stand-in, fixes the parity
+    fn solve_reduction(&self) -> Result<Vec<BlankMove>, SolveError> {
+        let mut board = self.clone();
+        let mut moves = Vec::new();
+        let first = (self.grid.len() - self.blanks.len() + 1) as u32;
+        let mut stand_ins: Vec<u32> = (first..self.grid.len() as u32).collect();
+        let mut real = 0;
+        let mut p = board.as_puzzle(real, &stand_ins);
+        if p.as_ref().is_some_and(|p| !p.is_solvable()) {
+            if stand_ins.len() >= 2 {
+                stand_ins.swap(0, 1);
+            } else {
+                // the swap only flips the parity at an even distance apart
+                let ((r0, c0), (r1, c1)) = (board.blanks[0], board.blanks[1]);
+                if (r0.abs_diff(r1) + c0.abs_diff(c1)) % 2 == 1 {
+                    let mv = Move::ALL
+                        .into_iter()
+                        .map(|dir| BlankMove { blank: 0, dir })
+                        .find(|&mv| board.is_legal(mv))
+                        .ok_or(SolveError::Unsolvable)?;
+                    board.perform(mv).map_err(|_| SolveError::Unsolvable)?;
+                    moves.push(mv);
+                }
+                real = 1;
+            }
+            p = board.as_puzzle(real, &stand_ins);
+        }
+        let mut p = p.ok_or(SolveError::Unsolvable)?;
+        // the blank the one-blank board's blank is
+        let mut blank = real;
+        for dir in p.solve()? {
+            let (row, col) = board.blanks[blank];
+            let Some(cell) = board.neighbour((row, col), dir) else {
+                return Err(p.violation("stand-in move left the board"));
+            };
+            match board.blanks.iter().position(|&b| b == cell) {
+                // a stand-in is really a blank, nothing moves
+                Some(other) => blank = other,
+                None => {
+                    let mv = BlankMove { blank, dir };
+                    board
+                        .perform(mv)
+                        .map_err(|_| p.violation("stand-in move was illegal"))?;
+                    moves.push(mv);
+                }
+            }
+        }
+        if !board.is_solved() {
+            return Err(p.violation("blanks not home after reduction"));
+        }
+        Ok(moves)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::puzzle::tables::{factorial, unrank};
+    use std::collections::VecDeque;
+
+    // every board reachable from the goal
+    fn reachable(goal: &MultiBlank) -> HashSet<Vec<u32>> {
+        let mut seen = HashSet::from([goal.grid.clone()]);
+        let mut queue = VecDeque::from([goal.clone()]);
+        while let Some(p) = queue.pop_front() {
+            for blank in 0..p.blanks.len() {
+                for dir in Move::ALL {
+                    let mut next = p.clone();
+                    if next.perform(BlankMove { blank, dir }).is_ok()
+                        && seen.insert(next.grid.clone())
+                    {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        seen
+    }
+
+    fn replay(p: &MultiBlank, moves: &[BlankMove]) -> bool {
+        let mut p = p.clone();
+        moves.iter().all(|&mv| p.perform(mv).is_ok()) && p.is_solved()
+    }
+
+    #[test]
+    fn test_moves() {
+        let mut p = MultiBlank::new(vec![vec![1, 0, 2], vec![0, 3, 4]]).unwrap();
+        assert_eq!(p.blanks(), &[(0, 1), (1, 0)]);
+        let down = BlankMove {
+            blank: 0,
+            dir: Move::Down,
+        };
+        p.perform(down).unwrap();
+        assert_eq!(p.tiles(), &[1, 3, 2, 0, 0, 4]);
+        // never into another blank
+        let left = BlankMove {
+            blank: 0,
+            dir: Move::Left,
+        };
+        assert_eq!(p.perform(left), Err(PuzzleError::IllegalMove(Move::Left)));
+        assert_eq!(
+            p.perform(BlankMove { blank: 2, ..down }),
+            Err(PuzzleError::NoSuchBlank(2))
+        );
+        // tile 4 moving left is blank 0 moving right
+        let mv = p.slide_tile(4, Move::Left).unwrap();
+        assert_eq!(
+            mv,
+            BlankMove {
+                blank: 0,
+                dir: Move::Right
+            }
+        );
+        assert_eq!(
+            p.slide_tile(1, Move::Up),
+            Err(PuzzleError::IllegalMove(Move::Up))
+        );
+        assert_eq!(
+            MultiBlank::from_tiles(2, 2, vec![1, 3, 0, 0]).unwrap_err(),
+            PuzzleError::InvalidTiles
+        );
+        assert!(MultiBlank::goal(2, 3, 2).unwrap().is_solved());
+    }
+
+    #[test]
+    fn test_solvability_rule() {
+        for (rows, cols, blanks) in [(2, 2, 2), (2, 3, 1), (2, 3, 2), (3, 3, 3), (1, 5, 2)] {
+            let goal = MultiBlank::goal(rows, cols, blanks).unwrap();
+            let reachable = reachable(&goal);
+            let tiles = goal.grid.len();
+            for r in 0..factorial(tiles) {
+                let tiles: Vec<u32> = unrank(r, tiles)
+                    .into_iter()
+                    .map(|t| goal.grid[t as usize])
+                    .collect();
+                let p = MultiBlank::from_tiles(rows, cols, tiles).unwrap();
+                assert_eq!(p.is_solvable(), reachable.contains(&p.grid), "{:?}", p.grid);
+            }
+        }
+    }
+
+    #[test]
+    fn test_extra_blanks_shorten_solutions() {
+        for seed in 0..5 {
+            let p = Puzzle::scrambled(3, 4, Scramble::Uniform, seed).unwrap();
+            let reduction = p.clone().solve().unwrap();
+            let m = MultiBlank::from_puzzle(&p, 2).unwrap();
+            let moves = m.solve().unwrap();
+            assert!(replay(&m, &moves));
+            assert!(moves.len() < reduction.len());
+            assert!(replay(&m, &m.solve_reduction().unwrap()));
+        }
+        // a search too small to finish falls back to the reduction
+        let p = Puzzle::scrambled(4, 4, Scramble::Uniform, 3).unwrap();
+        let m = MultiBlank::from_puzzle(&p, 3).unwrap();
+        let tiny = WeightedConfig {
+            max_states: 10,
+            ..WeightedConfig::default()
+        };
+        let moves = m.solve_with(&tiny, &mut SolveOptions::default()).unwrap();
+        assert!(replay(&m, &moves));
+        // only plain boards carry over
+        let walled = Puzzle::with_walls(3, 3, &[(1, 1)]).unwrap();
+        assert_eq!(
+            MultiBlank::from_puzzle(&walled, 2).unwrap_err(),
+            PuzzleError::InvalidTiles
+        );
+        let torus = Puzzle::goal(3, 3).unwrap().with_topology(Topology::Torus);
+        assert_eq!(
+            MultiBlank::from_puzzle(&torus, 2).unwrap_err(),
+            PuzzleError::InvalidTiles
+        );
+        let line = MultiBlank::new(vec![vec![2, 0, 1, 0]]).unwrap();
+        assert_eq!(line.solve(), Err(SolveError::Unsolvable));
+    }
+}
@@ -0,0 +1,513 @@
+/*
+ * colours.rs
+ * Boards whose tiles only carry a colour, tiles of one colour interchangeable
+ * the goal is any layout of the same colours with one blank
+ * solvability
+ *   - a line: the colours must already read in goal order
+ *   - a colour on two tiles: swapping which of them goes where flips the
+ *     parity, so every arrangement is solvable
+ *   - all colours different: the usual parity rule, against the goal
+ * solving
+ *   - reduction: name each tile after the goal cell it is sent to (one pair
+ *     of a colour swapped if the parity needs it), then the reduction solver,
+ *     going through the standard goal when the blank belongs elsewhere; the
+ *     shortest of the nearest goal cells and a few shuffles of them
+ *   - optimal: IDA* on each tile's distance to the nearest goal cell of its
+ *     colour
+ */
+
+use std::collections::HashMap;
+
+use crate::puzzle::pruning::INVERSE_ONLY;
+use crate::puzzle::random::Rng;
+use crate::puzzle::*;
+
+// ways of sending equal tiles to their goals the reduction tries
+const REDUCTION_TRIES: usize = 32;
+
+/* Board of coloured tiles with the layout it should reach */
+#[derive(Debug, Clone)]
+pub struct ColouredPuzzle {
+    grid: Vec<u32>,        // colour on each cell, 0 the blank
+    goal: Vec<u32>,        // colour each cell ends with
+    blank: (usize, usize), // (row,col) of blank piece
+    rows: usize,
+    cols: usize,
+}
+
+impl ColouredPuzzle {
+    pub fn new(in_grid: Vec<Vec<u32>>, goal: Vec<Vec<u32>>) -> Result<Self, PuzzleError> {
+        let (rows, cols, tiles) = flatten(in_grid)?;
+        let (goal_rows, goal_cols, goal) = flatten(goal)?;
+        if (goal_rows, goal_cols) != (rows, cols) {
+            return Err(PuzzleError::InvalidTiles);
+        }
+        Self::from_tiles(rows, cols, tiles, goal)
+    }
+
+    // Constructor from colours in row-major order, 0 the blank in both
+    pub fn from_tiles(
+        rows: usize,
+        cols: usize,
+        tiles: Vec<u32>,
+        goal: Vec<u32>,
+    ) -> Result<Self, PuzzleError> {
+        if rows == 0 || cols == 0 {
+            return Err(PuzzleError::EmptyBoard);
+        }
+        // one blank each and the same colours, as often
+        let mut sorted = tiles.clone();
+        let mut goal_sorted = goal.clone();
+        sorted.sort_unstable();
+        goal_sorted.sort_unstable();
+        let blanks = tiles.iter().filter(|&&c| c == 0).count();
+        if tiles.len() != rows * cols || sorted != goal_sorted || blanks != 1 {
+            return Err(PuzzleError::InvalidTiles);
+        }
+        let blank = tiles.iter().position(|&c| c == 0).unwrap_or(0);
+        Ok(Self {
+            grid: tiles,
+            goal,
+            blank: (blank / cols, blank % cols),
+            rows,
+            cols,
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    // colours in row-major order, 0 is the blank
+    pub fn tiles(&self) -> &[u32] {
+        &self.grid
+    }
+
+    pub fn goal(&self) -> &[u32] {
+        &self.goal
+    }
+
+    pub fn is_legal(&self, dir: Move) -> bool {
+        let (row, col) = self.blank;
+        match dir {
+            Move::Up => row > 0,
+            Move::Down => row + 1 < self.rows,
+            Move::Left => col > 0,
+            Move::Right => col + 1 < self.cols,
+            Move::Forward | Move::Back => false,
+        }
+    }
+
+    pub fn perform_move(&mut self, dir: Move) -> Result<(), PuzzleError> {
+        if !self.is_legal(dir) {
+            return Err(PuzzleError::IllegalMove(dir));
+        }
+        let (row, col) = self.blank;
+        self.blank = match dir {
+            Move::Up => (row - 1, col),
+            Move::Down => (row + 1, col),
+            Move::Left => (row, col - 1),
+            Move::Right => (row, col + 1),
+            Move::Forward | Move::Back => return Err(PuzzleError::IllegalMove(dir)),
+        };
+        let to = self.blank.0 * self.cols + self.blank.1;
+        self.grid.swap(row * self.cols + col, to);
+        Ok(())
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.grid == self.goal
+    }
+
+    pub fn is_solvable(&self) -> bool {
+        if self.rows == 1 || self.cols == 1 {
+            let colours =
+                |cells: &[u32]| -> Vec<u32> { cells.iter().copied().filter(|&c| c != 0).collect() };
+            return colours(&self.grid) == colours(&self.goal);
+        }
+        self.numbered(self.nearest_goals()).is_some()
+    }
+
+    // The board and its goal as numbered boards, tile t the one sent to the
+    // t-th non-blank goal cell, both solvable; None when no naming is
+    fn numbered(&self, mut sent: Vec<usize>) -> Option<(Puzzle, Puzzle)> {
+        let cells = self.grid.len();
+        let mut label = vec![0; cells];
+        for (t, cell) in (0..cells).filter(|&c| self.goal[c] != 0).enumerate() {
+            label[cell] = t as u32 + 1;
+        }
+        let named = |sent: &[usize]| -> Vec<u32> {
+            (0..cells)
+                .map(|c| if self.grid[c] == 0 { 0 } else { label[sent[c]] })
+                .collect()
+        };
+        let mut start = Puzzle::from_tiles(self.rows, self.cols, named(&sent)).ok()?;
+        let mut target = Puzzle::from_tiles(self.rows, self.cols, label.clone()).ok()?;
+        if start.is_solvable() != target.is_solvable() {
+            // two tiles of one colour trade goals, the cheapest such swap
+            let cost = |a: usize, b: usize| self.distance(a, b) as i64;
+            let pair = (0..cells)
+                .flat_map(|a| (a + 1..cells).map(move |b| (a, b)))
+                .filter(|&(a, b)| self.grid[a] != 0 && self.grid[a] == self.grid[b])
+                .min_by_key(|&(a, b)| {
+                    cost(a, sent[b]) + cost(b, sent[a]) - cost(a, sent[a]) - cost(b, sent[b])
+                })?;
+            sent.swap(pair.0, pair.1);
+            start = Puzzle::from_tiles(self.rows, self.cols, named(&sent)).ok()?;
+        }
+        if !target.is_solvable() {
+            // renaming two tiles in both flips both
+            let rename = |t: u32| match t {
+                1 => 2,
+                2 => 1,
+                t => t,
+            };
+            let swapped = |p: &Puzzle| p.tiles().iter().map(|&t| rename(t)).collect();
+            start = Puzzle::from_tiles(self.rows, self.cols, swapped(&start)).ok()?;
+            target = Puzzle::from_tiles(self.rows, self.cols, swapped(&target)).ok()?;
+        }
+        (start.is_solvable() && target.is_solvable()).then_some((start, target))
+    }
+
+    // goal cell for the tile on each cell (blank: unused), tiles of one colour
+    // in goal order on a line, else nearest first then pairwise improved
+    fn nearest_goals(&self) -> Vec<usize> {
+        let cells = self.grid.len();
+        let mut sent = vec![usize::MAX; cells];
+        let mut by_colour: HashMap<u32, (Vec<usize>, Vec<usize>)> = HashMap::new();
+        for c in 0..cells {
+            if self.grid[c] != 0 {
+                by_colour.entry(self.grid[c]).or_default().0.push(c);
+            }
+            if self.goal[c] != 0 {
+                by_colour.entry(self.goal[c]).or_default().1.push(c);
+            }
+        }
+        let line = self.rows == 1 || self.cols == 1;
+        for (from, mut to) in by_colour.into_values() {
+            if !line {
+                // nearest free goal cell first
+                let mut free = to;
+                to = Vec::new();
+                for &a in &from {
+                    let near = (0..free.len()).min_by_key(|&i| self.distance(a, free[i]));
+                    to.push(free.swap_remove(near.unwrap_or(0)));
+                }
+                // then swap pairs while that shortens the total
+                let mut better = true;
+                while better {
+                    better = false;
+                    for i in 0..from.len() {
+                        for j in i + 1..from.len() {
+                            let now = self.distance(from[i], to[i]) + self.distance(from[j], to[j]);
+                            let swapped =
+                                self.distance(from[i], to[j]) + self.distance(from[j], to[i]);
+                            if swapped < now {
+                                to.swap(i, j);
+                                better = true;
+                            }
+                        }
+                    }
+                }
+            }
+            for (a, b) in from.into_iter().zip(to) {
+                sent[a] = b;
+            }
+        }
+        sent
+    }
+
+    fn distance(&self, a: usize, b: usize) -> u32 {
+        let cols = self.cols;
+        ((a / cols).abs_diff(b / cols) + (a % cols).abs_diff(b % cols)) as u32
+    }
+
+    // same goals shuffled among the tiles of each colour
+    fn shuffled(&self, sent: &[usize], rng: &mut Rng) -> Vec<usize> {
+        let mut sent = sent.to_vec();
+        let mut colours: Vec<u32> = self.grid.iter().copied().filter(|&c| c != 0).collect();
+        colours.sort_unstable();
+        colours.dedup();
+        for colour in colours {
+            let cells: Vec<usize> = (0..self.grid.len())
+                .filter(|&c| self.grid[c] == colour)
+                .collect();
+            for i in (1..cells.len()).rev() {
+                sent.swap(cells[i], cells[rng.below(i + 1)]);
+            }
+        }
+        sent
+    }
+
+    // Reduction solver on the numbered board, the moves work for the colours;
+    // the shortest over a few ways of sending equal tiles to their goals
+    pub fn solve(&self) -> Result<Vec<Move>, SolveError> {
+        self.solve_with(&mut SolveOptions::default())
+    }
+
+    // Same, the options handed to every reduction it runs
+    pub fn solve_with(&self, opts: &mut SolveOptions) -> Result<Vec<Move>, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        let nearest = self.nearest_goals();
+        let tries = if self.rows == 1 || self.cols == 1 {
+            // a line allows only one
+            1
+        } else {
+            REDUCTION_TRIES
+        };
+        let mut rng = Rng::new(0);
+        let mut best: Option<Vec<Move>> = None;
+        for i in 0..tries {
+            let best_len = best.as_ref().map(|best| best.len() as u32);
+            opts.checkpoint(Progress::nodes(i as u64, best_len))?;
+            let sent = match i {
+                0 => nearest.clone(),
+                _ => self.shuffled(&nearest, &mut rng),
+            };
+            let Some((mut start, mut target)) = self.numbered(sent) else {
+                continue;
+            };
+            let mut moves = start.solve_with(opts)?;
+            if !target.is_solved() {
+                // the goal's own solution, played backwards
+                let back = target.solve_with(opts)?;
+                moves.extend(back.iter().rev().map(|dir| dir.inverse()));
+            }
+            if best.as_ref().is_none_or(|best| moves.len() < best.len()) {
+                best = Some(moves);
+            }
+        }
+        best.ok_or(SolveError::Unsolvable)
+    }
+
+    // Shortest solution, equal colours interchangeable
+    pub fn solve_optimal(&self) -> Result<Vec<Move>, SolveError> {
+        self.solve_optimal_with(&mut SolveOptions::default())
+    }
+
+    pub fn solve_optimal_with(&self, opts: &mut SolveOptions) -> Result<Vec<Move>, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        let pruner = MovePruner::build(self.rows, self.cols, INVERSE_ONLY);
+        // for each colour, each cell's distance to the nearest goal cell of it
+        let mut near: HashMap<u32, Vec<u32>> = HashMap::new();
+        for &colour in self.goal.iter().filter(|&&c| c != 0) {
+            near.entry(colour).or_insert_with(|| {
+                let goals: Vec<usize> = (0..self.goal.len())
+                    .filter(|&g| self.goal[g] == colour)
+                    .collect();
+                (0..self.goal.len())
+                    .map(|c| goals.iter().map(|&g| self.distance(c, g)).min())
+                    .map(|d| d.unwrap_or(0))
+                    .collect()
+            });
+        }
+        let mut ida = ColourIda {
+            puzzle: self.clone(),
+            near: &near,
+            path: Vec::new(),
+            nodes: 0,
+            pruner: &pruner,
+        };
+        let h = (0..self.grid.len())
+            .filter(|&c| self.grid[c] != 0)
+            .map(|c| near[&self.grid[c]][c])
+            .sum();
+        let mut bound = h;
+        loop {
+            opts.checkpoint(Progress::nodes(ida.nodes, Some(bound)))?;
+            let mut halt = |nodes, bound| opts.tick(nodes, Some(bound));
+            match ida.search(0, h, bound, MovePruner::START, &mut halt)? {
+                Some(next) => bound = next,
+                None => return Ok(ida.path),
+            }
+        }
+    }
+}
+
+// rows of a grid as (rows, cols, row-major cells)
+fn flatten(in_grid: Vec<Vec<u32>>) -> Result<(usize, usize, Vec<u32>), PuzzleError> {
+    let rows = in_grid.len();
+    let cols = in_grid.first().map_or(0, |row| row.len());
+    for (r, row) in in_grid.iter().enumerate() {
+        if row.len() != cols {
+            return Err(PuzzleError::RaggedRow {
+                row: r,
+                len: row.len(),
+                expected: cols,
+            });
+        }
+    }
+    Ok((rows, cols, in_grid.concat()))
+}
+
+struct ColourIda<'a> {
+    puzzle: ColouredPuzzle,
+    near: &'a HashMap<u32, Vec<u32>>,
+    path: Vec<Move>,
+    nodes: u64,
+    pruner: &'a MovePruner,
+}
+
+impl ColourIda<'_> {
+    // depth-first below f = bound: None once solved, else the smallest f above it
+    fn search<F>(
+        &mut self,
+        g: u32,
+        h: u32,
+        bound: u32,
+        state: u32,
+        halt: &mut F,
+    ) -> Result<Option<u32>, SolveError>
+    where
+        F: FnMut(u64, u32) -> Result<(), SolveError>,
+    {
+        self.nodes += 1;
+        halt(self.nodes, bound)?;
+        if g + h > bound {
+            return Ok(Some(g + h));
+        }
+        // every tile on a goal cell of its colour
+        if h == 0 {
+            return Ok(None);
+        }
+        let mut min = u32::MAX;
+        for dir in Move::ALL {
+            let Some(next_state) = self.pruner.step(state, dir) else {
+                continue;
+            };
+            let from = self.puzzle.blank.0 * self.puzzle.cols + self.puzzle.blank.1;
+            if self.puzzle.perform_move(dir).is_err() {
+                continue;
+            }
+            // the tile went from the blank's new cell to its old one
+            let to = self.puzzle.blank.0 * self.puzzle.cols + self.puzzle.blank.1;
+            let near = &self.near[&self.puzzle.grid[from]];
+            let next_h = h + near[from] - near[to];
+            self.path.push(dir);
+            match self.search(g + 1, next_h, bound, next_state, halt)? {
+                None => return Ok(None),
+                Some(f) => min = min.min(f),
+            }
+            self.path.pop();
+            let _ = self.puzzle.perform_move(dir.inverse());
+        }
+        Ok(Some(min))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::puzzle::tables::{factorial, unrank};
+    use std::collections::{HashSet, VecDeque};
+
+    // colour of tile t when each row of the goal is one colour
+    fn row_colours(p: &Puzzle) -> Vec<u32> {
+        let cols = p.cols() as u32;
+        p.tiles()
+            .iter()
+            .map(|&t| if t == 0 { 0 } else { (t - 1) / cols + 1 })
+            .collect()
+    }
+
+    fn replay(p: &ColouredPuzzle, moves: &[Move]) -> bool {
+        let mut p = p.clone();
+        moves.iter().all(|&dir| p.perform_move(dir).is_ok()) && p.is_solved()
+    }
+
+    // moves from every layout the blank can reach to the goal
+    fn distances(goal: &ColouredPuzzle) -> HashMap<Vec<u32>, usize> {
+        let mut dist = HashMap::from([(goal.grid.clone(), 0)]);
+        let mut queue = VecDeque::from([goal.clone()]);
+        while let Some(p) = queue.pop_front() {
+            let d = dist[&p.grid];
+            for dir in Move::ALL {
+                let mut next = p.clone();
+                if next.perform_move(dir).is_ok() && !dist.contains_key(&next.grid) {
+                    dist.insert(next.grid.clone(), d + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        dist
+    }
+
+    #[test]
+    fn test_solvability_and_optimum() {
+        let goals = [
+            (2, 3, vec![1, 1, 2, 2, 3, 0]), // pairs
+            (2, 3, vec![1, 2, 3, 4, 0, 5]), // all different, blank not last
+            (3, 3, vec![1, 1, 1, 2, 2, 2, 3, 3, 0]),
+            (1, 5, vec![1, 0, 2, 1, 2]), // a line
+        ];
+        for (rows, cols, goal) in goals {
+            let reached = distances(
+                &ColouredPuzzle::from_tiles(rows, cols, goal.clone(), goal.clone()).unwrap(),
+            );
+            let mut layouts = HashSet::new();
+            for r in 0..factorial(goal.len()) {
+                let tiles: Vec<u32> = unrank(r, goal.len())
+                    .into_iter()
+                    .map(|i| goal[i as usize])
+                    .collect();
+                if !layouts.insert(tiles.clone()) {
+                    continue;
+                }
+                let p = ColouredPuzzle::from_tiles(rows, cols, tiles, goal.clone()).unwrap();
+                assert_eq!(
+                    p.is_solvable(),
+                    reached.contains_key(&p.grid),
+                    "{:?}",
+                    p.grid
+                );
+                if !p.is_solvable() {
+                    assert_eq!(p.solve(), Err(SolveError::Unsolvable));
+                    continue;
+                }
+                // the 3x3 solutions are checked on a sample
+                if rows * cols < 9 || r % 97 == 0 {
+                    assert!(replay(&p, &p.solve().unwrap()), "{:?}", p.grid);
+                    let optimal = p.solve_optimal().unwrap();
+                    assert!(replay(&p, &optimal));
+                    assert_eq!(optimal.len(), reached[&p.grid]);
+                }
+            }
+        }
+        assert_eq!(
+            ColouredPuzzle::new(vec![vec![1, 0]], vec![vec![0, 2]]).unwrap_err(),
+            PuzzleError::InvalidTiles
+        );
+    }
+
+    #[test]
+    fn test_equal_colours_shorten_solutions() {
+        let (mut numbered, mut coloured) = (0, 0);
+        for seed in 0..10 {
+            let p = Puzzle::scrambled(4, 4, Scramble::Uniform, seed).unwrap();
+            let goal = row_colours(&Puzzle::goal(4, 4).unwrap());
+            let c = ColouredPuzzle::from_tiles(4, 4, row_colours(&p), goal).unwrap();
+            let moves = c.solve().unwrap();
+            assert!(replay(&c, &moves));
+            numbered += p.clone().solve().unwrap().len();
+            coloured += moves.len();
+        }
+        assert!(coloured < numbered);
+        // the optimum never needs more moves than with numbered tiles
+        for seed in 0..5 {
+            let p = Puzzle::scrambled(3, 3, Scramble::Uniform, seed).unwrap();
+            let goal = row_colours(&Puzzle::goal(3, 3).unwrap());
+            let c = ColouredPuzzle::from_tiles(3, 3, row_colours(&p), goal).unwrap();
+            let optimal = c.solve_optimal().unwrap();
+            assert!(replay(&c, &optimal));
+            assert!(optimal.len() <= p.solve_optimal().unwrap().len());
+        }
+    }
+}
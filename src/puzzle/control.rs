@@ -0,0 +1,203 @@
+/*
+ * control.rs
+ * Stopping long running solvers from the outside
+ * cancellation token, progress reports, wall-clock and node budgets
+ */
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use crate::puzzle::*;
+
+// searches look at the clock and report progress this often
+pub(crate) const CHECK_EVERY: u64 = 1 << 12;
+
+/* Shared flag, clones observe the same cancellation */
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/* Snapshot handed to the progress callback */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub nodes: u64,           // states expanded, moves made for the reduction solver
+    pub bound: Option<u32>,   // current cost bound or best length, when the search has one
+    pub phase: Option<Phase>, // reduction solver only
+    pub row_offset: usize,    // reduction solver window
+    pub col_offset: usize,
+}
+
+impl Progress {
+    pub(crate) fn nodes(nodes: u64, bound: Option<u32>) -> Self {
+        Self {
+            nodes,
+            bound,
+            phase: None,
+            row_offset: 0,
+            col_offset: 0,
+        }
+    }
+}
+
+/* Options accepted by every solve_*_with entry point */
+#[derive(Default)]
+pub struct SolveOptions<'a> {
+    pub cancel: Option<CancelToken>,
+    pub progress: Option<&'a mut dyn FnMut(&Progress)>,
+    pub deadline: Option<Instant>,
+    pub max_nodes: Option<u64>,
+}
+
+impl SolveOptions<'_> {
+    // search loops call this for every node, cheap unless a checkpoint is due
+    pub(crate) fn tick(&mut self, nodes: u64, bound: Option<u32>) -> Result<(), SolveError> {
+        if nodes.is_multiple_of(CHECK_EVERY) || self.max_nodes.is_some_and(|max| nodes > max) {
+            self.checkpoint(Progress::nodes(nodes, bound))?;
+        }
+        Ok(())
+    }
+
+    // report progress, then fail if the solve has to stop
+    pub(crate) fn checkpoint(&mut self, progress: Progress) -> Result<(), SolveError> {
+        if let Some(report) = self.progress.as_mut() {
+            report(&progress);
+        }
+        if self.should_stop() {
+            return Err(SolveError::Cancelled);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(SolveError::DeadlineExceeded);
+        }
+        match self.max_nodes {
+            Some(max) if progress.nodes > max => Err(SolveError::NodeLimitExceeded { nodes: max }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn should_stop(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    pub(crate) fn out_of_time(&self) -> bool {
+        self.should_stop()
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_clones_share_state() {
+        let token = CancelToken::new();
+        let other = token.clone();
+        assert!(!other.is_cancelled());
+        token.cancel();
+        assert!(other.is_cancelled());
+    }
+
+    #[test]
+    fn test_stop_reasons() {
+        let p = Puzzle::scrambled(4, 4, Scramble::Uniform, 3).unwrap();
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let mut opts = SolveOptions {
+            cancel: Some(cancel),
+            ..SolveOptions::default()
+        };
+        assert_eq!(p.clone().solve_with(&mut opts), Err(SolveError::Cancelled));
+        assert_eq!(p.solve_optimal_with(&mut opts), Err(SolveError::Cancelled));
+        // the other puzzles' solvers take the same options
+        assert_eq!(
+            p.to_graph().solve_with(&mut opts),
+            Err(SolveError::Cancelled)
+        );
+        let goal = Puzzle::goal(4, 4).unwrap().tiles().to_vec();
+        let colours = ColouredPuzzle::from_tiles(4, 4, p.tiles().to_vec(), goal).unwrap();
+        assert_eq!(colours.solve_with(&mut opts), Err(SolveError::Cancelled));
+        let table = DistanceTable::build(3, 3).unwrap();
+        let small = Puzzle::scrambled(3, 3, Scramble::Uniform, 3).unwrap();
+        assert_eq!(
+            table.solve_with(&small, &mut opts),
+            Err(SolveError::Cancelled)
+        );
+
+        let mut opts = SolveOptions {
+            max_nodes: Some(1000),
+            ..SolveOptions::default()
+        };
+        let hard = Puzzle::scrambled(4, 4, Scramble::Uniform, 7).unwrap();
+        assert_eq!(
+            hard.solve_optimal_with(&mut opts),
+            Err(SolveError::NodeLimitExceeded { nodes: 1000 })
+        );
+        assert_eq!(
+            hard.to_graph().solve_with(&mut opts),
+            Err(SolveError::NodeLimitExceeded { nodes: 1000 })
+        );
+        let mut opts = SolveOptions {
+            deadline: Some(Instant::now() + Duration::from_millis(50)),
+            ..SolveOptions::default()
+        };
+        let huge = Puzzle::scrambled(5, 5, Scramble::Uniform, 1).unwrap();
+        assert_eq!(
+            huge.solve_optimal_with(&mut opts),
+            Err(SolveError::DeadlineExceeded)
+        );
+    }
+
+    #[test]
+    fn test_progress_reports() {
+        let mut reports = Vec::new();
+        let mut record = |progress: &Progress| reports.push(*progress);
+        let mut opts = SolveOptions {
+            progress: Some(&mut record),
+            ..SolveOptions::default()
+        };
+        let mut p = Puzzle::scrambled(5, 4, Scramble::Uniform, 2).unwrap();
+        p.solve_with(&mut opts).unwrap();
+        drop(opts);
+        assert_eq!(reports[0].phase, Some(Phase::TopLeftCorner));
+        assert_eq!(reports.last().unwrap().phase, Some(Phase::Block2x2));
+        assert_eq!(
+            (
+                reports.last().unwrap().row_offset,
+                reports.last().unwrap().col_offset
+            ),
+            (3, 2)
+        );
+        assert!(reports.windows(2).all(|w| w[0].nodes <= w[1].nodes));
+
+        let mut bounds = Vec::new();
+        let mut record = |progress: &Progress| bounds.extend(progress.bound);
+        let mut opts = SolveOptions {
+            progress: Some(&mut record),
+            ..SolveOptions::default()
+        };
+        let p = Puzzle::scrambled(3, 3, Scramble::Uniform, 4).unwrap();
+        let moves = p.solve_optimal_with(&mut opts).unwrap();
+        drop(opts);
+        assert_eq!(*bounds.last().unwrap(), moves.len() as u32);
+    }
+}
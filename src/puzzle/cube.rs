@@ -0,0 +1,450 @@
+/*
+ * cube.rs
+ * Sliding puzzle in three dimensions: a layers x rows x cols box, one blank
+ * tile t belongs in cell t - 1 (layer by layer, each in reading order), the
+ * blank in the last cell; Forward / Back move the blank to the next / previous
+ * layer
+ * solvability
+ *   - a line: tiles never pass each other, they must already be in order
+ *   - otherwise the cells two-colour and (Wilson) every even arrangement is
+ *     reachable: the permutation, blank included, has the parity of the
+ *     blank's distance from its goal
+ * solving: the reduction of solver.rs one dimension up
+ *   - fix the face at the low end of the longest side, one tile at a time,
+ *     the last two of the face together, then recurse on what is left
+ *   - once no side is longer than 2: breadth first over the last few cells
+ */
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::puzzle::*;
+
+/* Board with one blank in a layers x rows x cols box */
+#[derive(Debug, Clone)]
+pub struct Cube {
+    grid: Vec<u32>,               // (layer * rows + row) * cols + col, 0 means blank
+    blank: (usize, usize, usize), // (layer,row,col) of blank
+    layers: usize,
+    rows: usize,
+    cols: usize,
+}
+
+impl Cube {
+    pub fn from_tiles(
+        layers: usize,
+        rows: usize,
+        cols: usize,
+        tiles: Vec<u32>,
+    ) -> Result<Self, PuzzleError> {
+        let cells = layers * rows * cols;
+        if cells == 0 {
+            return Err(PuzzleError::EmptyBoard);
+        }
+        let mut seen = vec![false; cells];
+        for &tile in &tiles {
+            match seen.get_mut(tile as usize) {
+                Some(slot) if !*slot => *slot = true,
+                _ => return Err(PuzzleError::InvalidTiles),
+            }
+        }
+        if tiles.len() != cells {
+            return Err(PuzzleError::InvalidTiles);
+        }
+        let at = tiles.iter().position(|&t| t == 0).unwrap_or(0);
+        Ok(Self {
+            grid: tiles,
+            blank: (at / (rows * cols), at / cols % rows, at % cols),
+            layers,
+            rows,
+            cols,
+        })
+    }
+
+    // Solved box
+    pub fn goal(layers: usize, rows: usize, cols: usize) -> Result<Self, PuzzleError> {
+        let cells = (layers * rows * cols) as u32;
+        let tiles = (1..=cells).map(|t| t % cells).collect();
+        Self::from_tiles(layers, rows, cols, tiles)
+    }
+
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn tiles(&self) -> &[u32] {
+        &self.grid
+    }
+
+    // (layer,row,col) of the blank
+    pub fn blank(&self) -> (usize, usize, usize) {
+        self.blank
+    }
+
+    fn index(&self, (layer, row, col): (usize, usize, usize)) -> usize {
+        (layer * self.rows + row) * self.cols + col
+    }
+
+    fn cell(&self, idx: usize) -> (usize, usize, usize) {
+        let plane = self.rows * self.cols;
+        (idx / plane, idx / self.cols % self.rows, idx % self.cols)
+    }
+
+    // cell next to (layer, row, col) in dir, if inside the box
+    fn neighbour(
+        &self,
+        (layer, row, col): (usize, usize, usize),
+        dir: Move,
+    ) -> Option<(usize, usize, usize)> {
+        match dir {
+            Move::Up if row > 0 => Some((layer, row - 1, col)),
+            Move::Down if row + 1 < self.rows => Some((layer, row + 1, col)),
+            Move::Left if col > 0 => Some((layer, row, col - 1)),
+            Move::Right if col + 1 < self.cols => Some((layer, row, col + 1)),
+            Move::Forward if layer + 1 < self.layers => Some((layer + 1, row, col)),
+            Move::Back if layer > 0 => Some((layer - 1, row, col)),
+            _ => None,
+        }
+    }
+
+    pub fn is_legal(&self, dir: Move) -> bool {
+        self.neighbour(self.blank, dir).is_some()
+    }
+
+    pub fn perform_move(&mut self, dir: Move) -> Result<(), PuzzleError> {
+        let Some(next) = self.neighbour(self.blank, dir) else {
+            return Err(PuzzleError::IllegalMove(dir));
+        };
+        let (from, to) = (self.index(self.blank), self.index(next));
+        self.grid.swap(from, to);
+        self.blank = next;
+        Ok(())
+    }
+
+    pub fn is_solved(&self) -> bool {
+        let last = self.grid.len() - 1;
+        self.grid[last] == 0 && (0..last).all(|i| self.grid[i] as usize == i + 1)
+    }
+
+    pub fn is_solvable(&self) -> bool {
+        let cells = self.grid.len() as u32;
+        let sides = [self.layers, self.rows, self.cols];
+        if sides.iter().filter(|&&side| side > 1).count() <= 1 {
+            // a line can only shift the blank, tiles keep their order
+            let tiles: Vec<u32> = self.grid.iter().copied().filter(|&t| t != 0).collect();
+            return tiles.is_sorted();
+        }
+        // the blank counts as the highest tile, so the goal has no inversions
+        let value = |t: u32| if t == 0 { cells } else { t };
+        let mut inversions = 0;
+        for i in 0..self.grid.len() {
+            for j in i + 1..self.grid.len() {
+                if value(self.grid[i]) > value(self.grid[j]) {
+                    inversions += 1;
+                }
+            }
+        }
+        let (layer, row, col) = self.blank;
+        let distance = (self.layers - 1 - layer) + (self.rows - 1 - row) + (self.cols - 1 - col);
+        inversions % 2 == distance % 2
+    }
+
+    // Layer by layer reduction, not the shortest
+    pub fn solve(&self) -> Result<Vec<Move>, SolveError> {
+        self.solve_with(&mut SolveOptions::default())
+    }
+
+    pub fn solve_with(&self, opts: &mut SolveOptions) -> Result<Vec<Move>, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        let mut reduction = Reduction {
+            cube: self.clone(),
+            free: vec![true; self.grid.len()],
+            moves: Vec::new(),
+            nodes: 0,
+        };
+        // window still to solve, [lo, hi) on each axis
+        let mut lo = [0; 3];
+        let hi = [self.layers, self.rows, self.cols];
+        loop {
+            // longest side, the layers first on ties
+            let axis = (0..3).rev().max_by_key(|&a| hi[a] - lo[a]).unwrap_or(0);
+            if hi[axis] - lo[axis] <= 2 {
+                break;
+            }
+            let face = self.window(lo, hi, Some(axis));
+            opts.checkpoint(Progress::nodes(reduction.nodes, None))?;
+            let mut i = 0;
+            while i < face.len() {
+                // the last cell of a face has one free neighbour: fill it with the one before
+                let cells = if face.len() > 1 && i + 2 == face.len() {
+                    &face[i..]
+                } else {
+                    &face[i..=i]
+                };
+                reduction.place(cells, opts)?;
+                i += cells.len();
+            }
+            lo[axis] += 1;
+        }
+        reduction.finish(&self.window(lo, hi, None), opts)?;
+        if !reduction.cube.is_solved() {
+            return Err(SolveError::SearchExhausted);
+        }
+        Ok(reduction.moves)
+    }
+
+    // cells of the window [lo, hi), only its low face on `face` if given, in reading order
+    fn window(&self, lo: [usize; 3], hi: [usize; 3], face: Option<usize>) -> Vec<usize> {
+        let mut hi = hi;
+        if let Some(axis) = face {
+            hi[axis] = lo[axis] + 1;
+        }
+        let mut cells = Vec::new();
+        for layer in lo[0]..hi[0] {
+            for row in lo[1]..hi[1] {
+                for col in lo[2]..hi[2] {
+                    cells.push(self.index((layer, row, col)));
+                }
+            }
+        }
+        cells
+    }
+}
+
+/* Board being reduced, with the cells already fixed */
+struct Reduction {
+    cube: Cube,
+    free: Vec<bool>,
+    moves: Vec<Move>,
+    nodes: u64,
+}
+
+impl Reduction {
+    // free cell next to idx in dir
+    fn step_from(&self, idx: usize, dir: Move) -> Option<usize> {
+        let next = self
+            .cube
+            .index(self.cube.neighbour(self.cube.cell(idx), dir)?);
+        self.free[next].then_some(next)
+    }
+
+    // Bring the goal tiles of `cells` home, breadth first over where the blank
+    // and those tiles are (the other free tiles may go anywhere), then fix them
+    fn place(&mut self, cells: &[usize], opts: &mut SolveOptions) -> Result<(), SolveError> {
+        let n = self.free.len();
+        // state: blank then each tracked tile, one base-n digit each
+        let encode = |at: &[usize]| at.iter().fold(0, |key, &cell| key * n + cell);
+        let mut start = vec![self.cube.index(self.cube.blank)];
+        for &cell in cells {
+            let tile = cell as u32 + 1;
+            start.push(self.cube.grid.iter().position(|&t| t == tile).unwrap_or(0));
+        }
+        let mut parent: HashMap<usize, (usize, Move)> = HashMap::new();
+        let start_key = encode(&start);
+        parent.insert(start_key, (start_key, Move::Up));
+        let mut queue = VecDeque::from([start]);
+        let mut found = None;
+        while let Some(at) = queue.pop_front() {
+            self.nodes += 1;
+            opts.tick(self.nodes, None)?;
+            if at[1..] == *cells {
+                found = Some(encode(&at));
+                break;
+            }
+            for dir in Move::SPATIAL {
+                let Some(next) = self.step_from(at[0], dir) else {
+                    continue;
+                };
+                let mut to = at.clone();
+                to[0] = next;
+                // a tracked tile in the way slides into the blank's cell
+                if let Some(slot) = to[1..].iter().position(|&cell| cell == next) {
+                    to[slot + 1] = at[0];
+                }
+                let key = encode(&to);
+                if let Entry::Vacant(entry) = parent.entry(key) {
+                    entry.insert((encode(&at), dir));
+                    queue.push_back(to);
+                }
+            }
+        }
+        let Some(mut key) = found else {
+            return Err(SolveError::SearchExhausted);
+        };
+        let mut path = Vec::new();
+        while key != start_key {
+            let (from, dir) = parent[&key];
+            path.push(dir);
+            key = from;
+        }
+        for dir in path.into_iter().rev() {
+            self.play(dir)?;
+        }
+        for &cell in cells {
+            self.free[cell] = false;
+        }
+        Ok(())
+    }
+
+    // Breadth first over the arrangements of the last few cells
+    fn finish(&mut self, cells: &[usize], opts: &mut SolveOptions) -> Result<(), SolveError> {
+        let goal: Vec<u32> = cells
+            .iter()
+            .map(|&cell| (cell as u32 + 1) % self.cube.grid.len() as u32)
+            .collect();
+        let arrangement =
+            |cube: &Cube| -> Vec<u32> { cells.iter().map(|&c| cube.grid[c]).collect() };
+        // (board, parent, move from parent)
+        let mut nodes = vec![(self.cube.clone(), usize::MAX, None)];
+        let mut seen = HashSet::from([arrangement(&self.cube)]);
+        let mut queue = VecDeque::from([0]);
+        while let Some(i) = queue.pop_front() {
+            self.nodes += 1;
+            opts.tick(self.nodes, None)?;
+            if arrangement(&nodes[i].0) == goal {
+                let mut path = Vec::new();
+                let mut at = i;
+                while let (_, parent, Some(dir)) = nodes[at] {
+                    path.push(dir);
+                    at = parent;
+                }
+                for dir in path.into_iter().rev() {
+                    self.play(dir)?;
+                }
+                return Ok(());
+            }
+            let blank = self.cube.index(nodes[i].0.blank);
+            for dir in Move::SPATIAL {
+                if self.step_from(blank, dir).is_none() {
+                    continue;
+                }
+                let mut next = nodes[i].0.clone();
+                next.perform_move(dir)
+                    .map_err(|_| SolveError::SearchExhausted)?;
+                if seen.insert(arrangement(&next)) {
+                    queue.push_back(nodes.len());
+                    nodes.push((next, i, Some(dir)));
+                }
+            }
+        }
+        Err(SolveError::SearchExhausted)
+    }
+
+    fn play(&mut self, dir: Move) -> Result<(), SolveError> {
+        self.cube
+            .perform_move(dir)
+            .map_err(|_| SolveError::SearchExhausted)?;
+        self.moves.push(dir);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::puzzle::random::Rng;
+
+    // uniform arrangement, two tiles swapped back if it came out unsolvable
+    fn shuffled(layers: usize, rows: usize, cols: usize, seed: u64) -> Cube {
+        let mut rng = Rng::new(seed);
+        let mut tiles = Cube::goal(layers, rows, cols).unwrap().tiles().to_vec();
+        for i in (1..tiles.len()).rev() {
+            tiles.swap(i, rng.below(i + 1));
+        }
+        let cube = Cube::from_tiles(layers, rows, cols, tiles.clone()).unwrap();
+        if cube.is_solvable() {
+            return cube;
+        }
+        let pair: Vec<usize> = (0..tiles.len())
+            .filter(|&i| tiles[i] != 0)
+            .take(2)
+            .collect();
+        tiles.swap(pair[0], pair[1]);
+        Cube::from_tiles(layers, rows, cols, tiles).unwrap()
+    }
+
+    fn replay(cube: &Cube, moves: &[Move]) -> Cube {
+        let mut replay = cube.clone();
+        for &dir in moves {
+            replay.perform_move(dir).unwrap();
+        }
+        replay
+    }
+
+    #[test]
+    fn test_layers_and_moves() {
+        let mut cube = Cube::goal(2, 2, 2).unwrap();
+        assert!(cube.is_solved());
+        assert_eq!(cube.blank(), (1, 1, 1));
+        assert!(!cube.is_legal(Move::Forward) && !cube.is_legal(Move::Down));
+        cube.perform_move(Move::Back).unwrap();
+        assert_eq!(cube.tiles(), &[1, 2, 3, 0, 5, 6, 7, 4]);
+        assert_eq!(
+            cube.perform_move(Move::Back),
+            Err(PuzzleError::IllegalMove(Move::Back))
+        );
+        cube.perform_move(Move::Forward).unwrap();
+        assert!(cube.is_solved());
+        assert_eq!(
+            Cube::from_tiles(2, 2, 2, vec![1, 2, 3, 4, 5, 6, 7, 7]).unwrap_err(),
+            PuzzleError::InvalidTiles
+        );
+        assert_eq!(
+            Cube::from_tiles(0, 2, 2, vec![]).unwrap_err(),
+            PuzzleError::EmptyBoard
+        );
+    }
+
+    #[test]
+    fn test_solvability_rule() {
+        for (layers, rows, cols) in [(2, 2, 2), (2, 1, 3), (3, 1, 1), (1, 2, 3)] {
+            let start = Cube::goal(layers, rows, cols).unwrap();
+            let mut seen = HashSet::from([start.tiles().to_vec()]);
+            let mut stack = vec![start];
+            while let Some(cube) = stack.pop() {
+                for dir in Move::SPATIAL {
+                    let mut next = cube.clone();
+                    if next.perform_move(dir).is_ok() && seen.insert(next.tiles().to_vec()) {
+                        stack.push(next);
+                    }
+                }
+            }
+            let cells = layers * rows * cols;
+            for r in 0..crate::puzzle::tables::factorial(cells) {
+                let tiles = crate::puzzle::tables::unrank(r, cells);
+                let cube = Cube::from_tiles(layers, rows, cols, tiles).unwrap();
+                assert_eq!(cube.is_solvable(), seen.contains(cube.tiles()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_solves_boxes() {
+        for (layers, rows, cols) in [(2, 2, 2), (3, 3, 3), (2, 3, 4), (4, 2, 3), (1, 3, 4)] {
+            for seed in 0..4 {
+                let cube = shuffled(layers, rows, cols, seed);
+                let moves = cube.solve().unwrap();
+                assert!(replay(&cube, &moves).is_solved());
+            }
+        }
+        // one swap away from solved
+        let mut tiles = Cube::goal(3, 3, 3).unwrap().tiles().to_vec();
+        tiles.swap(0, 1);
+        let cube = Cube::from_tiles(3, 3, 3, tiles).unwrap();
+        assert!(!cube.is_solvable());
+        assert_eq!(cube.solve(), Err(SolveError::Unsolvable));
+        // a line solves by walking the blank home
+        let line = Cube::from_tiles(3, 1, 1, vec![0, 1, 2]).unwrap();
+        assert_eq!(line.solve().unwrap(), vec![Move::Forward, Move::Forward]);
+    }
+}
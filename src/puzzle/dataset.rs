@@ -0,0 +1,369 @@
+/*
+ * dataset.rs
+ * Labelled states for training learned heuristics offline
+ * each sample carries its exact distance and an optimal next move
+ * labels come from a DistanceTable on small boards, otherwise IDA* on the
+ * strongest heuristic, within an optional node budget per sample
+ * streamed as CSV or a compact binary format, reproducible from a seed
+ */
+
+use std::fmt;
+use std::io::{Read, Write};
+
+use crate::puzzle::random::Rng;
+use crate::puzzle::tables::{factorial, unrank};
+use crate::puzzle::*;
+
+// boards up to this size are labelled from a distance table
+pub const TABLE_LABEL_CELLS: usize = 9;
+
+const MAGIC: &[u8; 4] = b"P15D";
+const VERSION: u8 = 1;
+const NO_MOVE: u8 = u8::MAX;
+
+#[derive(Debug)]
+pub enum DatasetError {
+    Table(TableError),
+    Puzzle(PuzzleError),
+    Solve(SolveError),
+    Io(std::io::Error),
+    BadFormat(&'static str),
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatasetError::Table(err) => write!(f, "{}", err),
+            DatasetError::Puzzle(err) => write!(f, "{}", err),
+            DatasetError::Solve(err) => write!(f, "{}", err),
+            DatasetError::Io(err) => write!(f, "dataset io failed: {}", err),
+            DatasetError::BadFormat(reason) => write!(f, "bad dataset file: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {}
+
+impl From<TableError> for DatasetError {
+    fn from(err: TableError) -> Self {
+        DatasetError::Table(err)
+    }
+}
+
+impl From<PuzzleError> for DatasetError {
+    fn from(err: PuzzleError) -> Self {
+        DatasetError::Puzzle(err)
+    }
+}
+
+impl From<SolveError> for DatasetError {
+    fn from(err: SolveError) -> Self {
+        DatasetError::Solve(err)
+    }
+}
+
+impl From<std::io::Error> for DatasetError {
+    fn from(err: std::io::Error) -> Self {
+        DatasetError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatasetConfig {
+    pub rows: usize,
+    pub cols: usize,
+    // Uniform draws a uniform permutation rank when a table is available
+    pub scramble: Scramble,
+    pub seed: u64,
+    // search labels give up past this many nodes per sample, None for no limit
+    pub max_nodes: Option<u64>,
+}
+
+/* One labelled state */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub tiles: Vec<u32>, // row-major, 0 is the blank
+    pub distance: u32,
+    pub next_move: Option<Move>, // None only for the goal
+}
+
+enum Oracle {
+    Table(DistanceTable),
+    Search(MaxOf),
+}
+
+/* Endless, seeded stream of samples */
+pub struct Dataset {
+    config: DatasetConfig,
+    rng: Rng,
+    oracle: Oracle,
+}
+
+impl Dataset {
+    pub fn new(config: DatasetConfig) -> Result<Self, DatasetError> {
+        // a board that cannot be built is a board error, not a table or search one
+        Puzzle::goal(config.rows, config.cols)?;
+        let oracle = if config.rows * config.cols <= TABLE_LABEL_CELLS {
+            Oracle::Table(DistanceTable::build(config.rows, config.cols)?)
+        } else {
+            Oracle::Search(MaxOf::strongest(config.rows, config.cols))
+        };
+        Ok(Self::with_oracle(config, oracle))
+    }
+
+    // reuse a table that was already built or loaded from disk
+    pub fn with_table(config: DatasetConfig, table: DistanceTable) -> Result<Self, DatasetError> {
+        if (table.rows(), table.cols()) != (config.rows, config.cols) {
+            return Err(DatasetError::BadFormat("table does not match the board"));
+        }
+        Ok(Self::with_oracle(config, Oracle::Table(table)))
+    }
+
+    fn with_oracle(config: DatasetConfig, oracle: Oracle) -> Self {
+        Self {
+            rng: Rng::new(config.seed),
+            config,
+            oracle,
+        }
+    }
+
+    pub fn next_sample(&mut self) -> Result<Sample, DatasetError> {
+        let (rows, cols) = (self.config.rows, self.config.cols);
+        let p = match (&self.oracle, self.config.scramble) {
+            (Oracle::Table(table), Scramble::Uniform) => loop {
+                // uniform over ranks, skipping the unsolvable half
+                let r = self.rng.below(factorial(rows * cols));
+                if table.distances()[r] != UNREACHABLE {
+                    break Puzzle::from_tiles(rows, cols, unrank(r, rows * cols))?;
+                }
+            },
+            (_, scramble) => Puzzle::scrambled_with(rows, cols, scramble, &mut self.rng)?,
+        };
+        let (distance, next_move) = match &self.oracle {
+            Oracle::Table(table) => {
+                let d = table.distance(&p).ok_or(SolveError::Unsolvable)?;
+                (d as u32, table.next_move(&p))
+            }
+            Oracle::Search(heuristic) => {
+                let mut opts = SolveOptions {
+                    max_nodes: self.config.max_nodes,
+                    ..SolveOptions::default()
+                };
+                let moves = p
+                    .solve_optimal_heuristic_with(heuristic, &mut opts)
+                    .map_err(|err| match err {
+                        // out of budget: this sample has no exact label
+                        SolveError::NodeLimitExceeded { .. } => SolveError::SearchExhausted,
+                        err => err,
+                    })?;
+                (moves.len() as u32, moves.first().copied())
+            }
+        };
+        Ok(Sample {
+            tiles: p.grid,
+            distance,
+            next_move,
+        })
+    }
+
+    // header, then one `tile..., distance, next_move` line per sample
+    pub fn write_csv(&mut self, count: usize, mut out: impl Write) -> Result<(), DatasetError> {
+        let cells = self.config.rows * self.config.cols;
+        let header: Vec<String> = (0..cells).map(|i| format!("c{}", i)).collect();
+        writeln!(out, "{},distance,next_move", header.join(","))?;
+        for _ in 0..count {
+            let sample = self.next_sample()?;
+            let tiles: Vec<String> = sample.tiles.iter().map(|t| t.to_string()).collect();
+            let next = sample
+                .next_move
+                .map_or(String::new(), |m| format!("{:?}", m));
+            writeln!(out, "{},{},{}", tiles.join(","), sample.distance, next)?;
+        }
+        Ok(())
+    }
+
+    // magic, version, rows, cols, count (u32 LE)
+    // then per sample: one byte per tile, distance (u16 LE), move code
+    pub fn write_binary(&mut self, count: usize, mut out: impl Write) -> Result<(), DatasetError> {
+        let (rows, cols) = (self.config.rows, self.config.cols);
+        let max = u8::MAX as usize;
+        if rows > max || cols > max || rows * cols > max + 1 {
+            return Err(DatasetError::BadFormat(
+                "board too large for the binary format",
+            ));
+        }
+        let Ok(stored) = u32::try_from(count) else {
+            return Err(DatasetError::BadFormat(
+                "too many samples for the binary format",
+            ));
+        };
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION, rows as u8, cols as u8])?;
+        out.write_all(&stored.to_le_bytes())?;
+        for _ in 0..count {
+            let sample = self.next_sample()?;
+            let tiles: Vec<u8> = sample.tiles.iter().map(|&t| t as u8).collect();
+            out.write_all(&tiles)?;
+            out.write_all(&(sample.distance as u16).to_le_bytes())?;
+            let code = match sample.next_move {
+                None => NO_MOVE,
+                Some(dir) => move_code(dir)
+                    .ok_or(DatasetError::BadFormat("moves between layers have no code"))?,
+            };
+            out.write_all(&[code])?;
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Dataset {
+    type Item = Result<Sample, DatasetError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_sample())
+    }
+}
+
+// read back a file produced by write_binary: (rows, cols, samples)
+pub fn read_binary(mut input: impl Read) -> Result<(usize, usize, Vec<Sample>), DatasetError> {
+    let mut header = [0u8; 11];
+    input.read_exact(&mut header)?;
+    if &header[0..4] != MAGIC {
+        return Err(DatasetError::BadFormat("wrong magic"));
+    }
+    if header[4] != VERSION {
+        return Err(DatasetError::BadFormat("unknown version"));
+    }
+    let (rows, cols) = (header[5] as usize, header[6] as usize);
+    let count = u32::from_le_bytes([header[7], header[8], header[9], header[10]]) as usize;
+    let mut record = vec![0u8; rows * cols + 3];
+    let mut samples = Vec::with_capacity(count);
+    for _ in 0..count {
+        input.read_exact(&mut record)?;
+        let (tiles, label) = record.split_at(rows * cols);
+        let next_move = match label[2] {
+            NO_MOVE => None,
+            code => Some(
+                *Move::ALL
+                    .get(code as usize)
+                    .ok_or(DatasetError::BadFormat("unknown move code"))?,
+            ),
+        };
+        samples.push(Sample {
+            tiles: tiles.iter().map(|&t| t as u32).collect(),
+            distance: u16::from_le_bytes([label[0], label[1]]) as u32,
+            next_move,
+        });
+    }
+    Ok((rows, cols, samples))
+}
+
+// index into Move::ALL, None for a move between layers
+fn move_code(dir: Move) -> Option<u8> {
+    match dir {
+        Move::Up => Some(0),
+        Move::Down => Some(1),
+        Move::Left => Some(2),
+        Move::Right => Some(3),
+        Move::Forward | Move::Back => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(rows: usize, cols: usize, scramble: Scramble) -> DatasetConfig {
+        DatasetConfig {
+            rows,
+            cols,
+            scramble,
+            seed: 42,
+            max_nodes: None,
+        }
+    }
+
+    #[test]
+    fn test_labels_are_exact() {
+        let table = DistanceTable::build(2, 3).unwrap();
+        let data = Dataset::with_table(config(2, 3, Scramble::Uniform), table.clone()).unwrap();
+        for sample in data.take(100) {
+            let sample = sample.unwrap();
+            let p = Puzzle::from_tiles(2, 3, sample.tiles).unwrap();
+            assert_eq!(table.distance(&p), Some(sample.distance as u8));
+            if let Some(dir) = sample.next_move {
+                let mut next = p.clone();
+                next.perform_move(dir).unwrap();
+                assert_eq!(table.distance(&next), Some(sample.distance as u8 - 1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_labels() {
+        let mut data = Dataset::new(config(4, 4, Scramble::Walk(12))).unwrap();
+        for _ in 0..5 {
+            let sample = data.next_sample().unwrap();
+            assert!(sample.distance <= 12 && sample.distance.is_multiple_of(2));
+        }
+        // a uniform 4x4 board is far too deep for a small budget
+        let budget = DatasetConfig {
+            max_nodes: Some(1000),
+            ..config(4, 4, Scramble::Uniform)
+        };
+        assert!(matches!(
+            Dataset::new(budget).unwrap().next_sample(),
+            Err(DatasetError::Solve(SolveError::SearchExhausted))
+        ));
+    }
+
+    #[test]
+    fn test_reproducible_csv() {
+        let mut a = Vec::new();
+        let mut b = Vec::new();
+        Dataset::new(config(2, 2, Scramble::Uniform))
+            .unwrap()
+            .write_csv(5, &mut a)
+            .unwrap();
+        Dataset::new(config(2, 2, Scramble::Uniform))
+            .unwrap()
+            .write_csv(5, &mut b)
+            .unwrap();
+        assert_eq!(a, b);
+        let text = String::from_utf8(a).unwrap();
+        assert_eq!(text.lines().count(), 6);
+        assert!(text.starts_with("c0,c1,c2,c3,distance,next_move\n"));
+    }
+
+    #[test]
+    fn test_binary_roundtrip() {
+        let mut bytes = Vec::new();
+        Dataset::new(config(3, 2, Scramble::Walk(8)))
+            .unwrap()
+            .write_binary(10, &mut bytes)
+            .unwrap();
+        assert_eq!(bytes.len(), 11 + 10 * (6 + 3));
+        let (rows, cols, samples) = read_binary(&bytes[..]).unwrap();
+        let expected: Vec<Sample> = Dataset::new(config(3, 2, Scramble::Walk(8)))
+            .unwrap()
+            .take(10)
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!((rows, cols), (3, 2));
+        assert_eq!(samples, expected);
+        // the header has room for a u32 count only
+        let mut data = Dataset::new(config(3, 2, Scramble::Walk(8))).unwrap();
+        assert!(matches!(
+            data.write_binary(u32::MAX as usize + 1, Vec::new()),
+            Err(DatasetError::BadFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_board_errors_pass_through() {
+        assert!(matches!(
+            Dataset::new(config(0, 3, Scramble::Walk(8))),
+            Err(DatasetError::Puzzle(PuzzleError::EmptyBoard))
+        ));
+    }
+}
@@ -0,0 +1,268 @@
+/*
+ * env.rs
+ * Gym-style environment around Puzzle for training agents
+ * reset(seed) -> observation, step(Move) -> observation, reward, done, illegal
+ */
+
+use crate::puzzle::random::Rng;
+use crate::puzzle::*;
+
+const RESET_ATTEMPTS: usize = 16;
+
+/* How a board is turned into numbers */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    // one value per cell, the tile number
+    Flat,
+    // one rows*cols wide one-hot vector per cell
+    OneHot,
+}
+
+/* Extra reward on top of the per-step penalty */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shaping {
+    None,
+    // decrease in Manhattan distance caused by the move
+    ManhattanDelta,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvConfig {
+    pub rows: usize,
+    pub cols: usize,
+    pub scramble: Scramble,
+    pub encoding: Encoding,
+    pub shaping: Shaping,
+    pub max_steps: usize, // episode is truncated after this many steps
+    pub step_reward: f32,
+    pub illegal_reward: f32, // added when the move would leave the board
+    pub solved_reward: f32,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            rows: 4,
+            cols: 4,
+            scramble: Scramble::Uniform,
+            encoding: Encoding::Flat,
+            shaping: Shaping::None,
+            max_steps: 200,
+            step_reward: -1.0,
+            illegal_reward: -1.0,
+            solved_reward: 0.0,
+        }
+    }
+}
+
+/* Result of a single step */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub observation: Vec<f32>,
+    pub reward: f32,
+    pub done: bool,      // solved or truncated
+    pub truncated: bool, // hit max_steps without solving
+    pub illegal: bool,   // move was rejected, board unchanged
+}
+
+#[derive(Debug, Clone)]
+pub struct PuzzleEnv {
+    config: EnvConfig,
+    puzzle: Puzzle,
+    steps: usize,
+    done: bool,
+    truncated: bool, // how the finished episode ended
+}
+
+impl PuzzleEnv {
+    pub fn new(config: EnvConfig) -> Result<Self, PuzzleError> {
+        let puzzle = Puzzle::goal(config.rows, config.cols)?;
+        Ok(Self {
+            config,
+            puzzle,
+            steps: 0,
+            done: true,
+            truncated: false,
+        })
+    }
+
+    pub const ACTIONS: [Move; 4] = Move::ALL;
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    pub fn puzzle(&self) -> &Puzzle {
+        &self.puzzle
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    // Start a new episode on a solvable, unsolved scramble
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        let (rows, cols) = (self.config.rows, self.config.cols);
+        let mut rng = Rng::new(seed);
+        // redraw solved scrambles, a few tries is plenty (Walk(0) never leaves the goal)
+        for _ in 0..RESET_ATTEMPTS {
+            let Ok(p) = Puzzle::scrambled_with(rows, cols, self.config.scramble, &mut rng) else {
+                break;
+            };
+            self.puzzle = p;
+            if !self.puzzle.is_solved() {
+                break;
+            }
+        }
+        self.steps = 0;
+        self.done = self.puzzle.is_solved();
+        self.truncated = false;
+        self.observation()
+    }
+
+    pub fn step(&mut self, dir: Move) -> Step {
+        if self.done {
+            return Step {
+                observation: self.observation(),
+                reward: 0.0,
+                done: true,
+                truncated: self.truncated,
+                illegal: false,
+            };
+        }
+        let before = self.puzzle.manhattan();
+        let illegal = self.puzzle.perform_move(dir).is_err();
+        self.steps += 1;
+        let mut reward = self.config.step_reward;
+        if illegal {
+            reward += self.config.illegal_reward;
+        }
+        if self.config.shaping == Shaping::ManhattanDelta {
+            reward += before as f32 - self.puzzle.manhattan() as f32;
+        }
+        let solved = self.puzzle.is_solved();
+        if solved {
+            reward += self.config.solved_reward;
+        }
+        self.truncated = !solved && self.steps >= self.config.max_steps;
+        self.done = solved || self.truncated;
+        Step {
+            observation: self.observation(),
+            reward,
+            done: self.done,
+            truncated: self.truncated,
+            illegal,
+        }
+    }
+
+    pub fn observation_len(&self) -> usize {
+        let cells = self.config.rows * self.config.cols;
+        match self.config.encoding {
+            Encoding::Flat => cells,
+            Encoding::OneHot => cells * cells,
+        }
+    }
+
+    pub fn observation(&self) -> Vec<f32> {
+        encode(&self.puzzle, self.config.encoding)
+    }
+}
+
+pub fn encode(p: &Puzzle, encoding: Encoding) -> Vec<f32> {
+    match encoding {
+        // walls read as -1, or as a cell with nothing on it
+        Encoding::Flat => p
+            .grid
+            .iter()
+            .map(|&t| if t == WALL { -1.0 } else { t as f32 })
+            .collect(),
+        Encoding::OneHot => {
+            let cells = p.grid.len();
+            let mut out = vec![0.0; cells * cells];
+            for (idx, &tile) in p.grid.iter().enumerate() {
+                if tile != WALL {
+                    out[idx * cells + tile as usize] = 1.0;
+                }
+            }
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_episode() {
+        let config = EnvConfig {
+            rows: 2,
+            cols: 2,
+            ..EnvConfig::default()
+        };
+        let mut env = PuzzleEnv::new(config).unwrap();
+        let obs = env.reset(3);
+        assert_eq!(obs.len(), env.observation_len());
+        assert!(env.puzzle().is_solvable() && !env.puzzle().is_solved());
+        // replay the reduction solution through the environment
+        let moves = env.puzzle().clone().solve().unwrap();
+        let last = moves.len() - 1;
+        for (i, dir) in moves.into_iter().enumerate() {
+            let step = env.step(dir);
+            assert!(!step.illegal);
+            assert_eq!(step.done, i == last);
+        }
+        assert!(!env.step(Move::Up).reward.is_nan());
+    }
+
+    #[test]
+    fn test_illegal_and_truncation() {
+        let config = EnvConfig {
+            rows: 3,
+            cols: 3,
+            max_steps: 2,
+            scramble: Scramble::Walk(10),
+            ..EnvConfig::default()
+        };
+        let mut env = PuzzleEnv::new(config).unwrap();
+        env.reset(1);
+        let blocked = Move::ALL
+            .into_iter()
+            .find(|&dir| !env.puzzle().is_legal(dir))
+            .unwrap();
+        let before = env.puzzle().tiles().to_vec();
+        let step = env.step(blocked);
+        assert!(step.illegal && !step.done);
+        assert_eq!(step.reward, -2.0);
+        assert_eq!(env.puzzle().tiles(), &before[..]);
+        let step = env.step(blocked);
+        assert!(step.done && step.truncated);
+        // the finished episode stays truncated
+        let step = env.step(blocked);
+        assert!(step.done && step.truncated && step.reward == 0.0);
+        env.reset(2);
+        assert!(!env.step(blocked).truncated);
+    }
+
+    #[test]
+    fn test_encodings_and_shaping() {
+        let p = Puzzle::new(vec![vec![1, 0], vec![3, 2]]).unwrap();
+        assert_eq!(encode(&p, Encoding::Flat), vec![1.0, 0.0, 3.0, 2.0]);
+        let one_hot = encode(&p, Encoding::OneHot);
+        assert_eq!(one_hot.iter().sum::<f32>(), 4.0);
+        assert_eq!(one_hot[4], 1.0); // cell 1 holds the blank
+
+        let config = EnvConfig {
+            rows: 2,
+            cols: 2,
+            shaping: Shaping::ManhattanDelta,
+            ..EnvConfig::default()
+        };
+        let mut env = PuzzleEnv::new(config).unwrap();
+        env.puzzle = p;
+        env.done = false;
+        let step = env.step(Move::Down);
+        assert!(step.done && !step.truncated);
+        assert_eq!(step.reward, -1.0 + 1.0);
+    }
+}
@@ -0,0 +1,1330 @@
+/*
+ * graph.rs
+ * Sliding puzzles on any undirected graph (pebble motion)
+ * one piece on every vertex but one, piece t belongs on vertex t - 1 and the
+ * empty vertex is the last; a move slides a piece along an edge into it
+ * solvability (Wilson), on the component holding the empty vertex
+ *   - pieces in the other components never move, they must already be home
+ *   - a path keeps the order of its pieces, a cycle their cyclic order
+ *   - any other 2-connected graph: a two-coloured one keeps the parity of the
+ *     permutation against the colour of the empty vertex, theta-0 (a hexagon
+ *     with a path of length 2 across) reaches only some arrangements and is
+ *     searched, every other one reaches them all
+ *   - with a cut vertex: no piece gets past one, so once the empty vertex is
+ *     walked home each block (2-connected piece, or lone edge) holds its own
+ *     pieces for good and is decided alone as above, its empty vertex on the
+ *     vertex nearest home (Kornhauser, Miller, Spirakis); the solver falls
+ *     back to a weighted search and reports a stuck board as exhausted
+ * solving a 2-connected graph
+ *   - peel off vertices (or ears, paths of degree-2 vertices) furthest from
+ *     the empty vertex's home first, each leaving the rest 2-connected and
+ *     with an odd cycle if it had one, so Wilson says the rest is still
+ *     solvable; the order is planned over the shape alone
+ *   - a vertex's piece goes home by breadth first search over where it and
+ *     the empty vertex are, an ear is filled like a queue from one end
+ *   - breadth first over what is left once it is small; a rest that will not
+ *     peel (a long cycle with a path across) takes each piece home with a
+ *     conjugated 3-cycle
+ * any Puzzle converts, vertex i is the i-th free cell in reading order
+ */
+
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::puzzle::*;
+
+// peeling stops at this many vertices, the rest is searched
+const FINISH_VERTICES: usize = 8;
+// what is left is searched breadth first when small, else weighted like this
+const FINISH_WEIGHT: u32 = 2;
+// states the final search may store
+const MAX_FINISH_STATES: usize = 1 << 20;
+// shapes the peeling plan may try
+const MAX_PLAN_NODES: usize = 2000;
+// commutators moving at most this many pieces, the shortest few, are paired
+// up when looking for a 3-cycle
+const MAX_MOVED: usize = 8;
+const MAX_COMMUTATORS: usize = 256;
+
+/* Errors when building or moving on a graph */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphError {
+    EmptyGraph,
+    EdgeOutOfRange { from: usize, to: usize },
+    SelfLoop(usize),
+    // pieces must be exactly 0..vertices, each once
+    InvalidPieces,
+    // the vertex is not next to the empty one
+    IllegalSlide(usize),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::EmptyGraph => write!(f, "graph has no vertices"),
+            GraphError::EdgeOutOfRange { from, to } => {
+                write!(f, "edge ({}, {}) leaves the graph", from, to)
+            }
+            GraphError::SelfLoop(v) => write!(f, "vertex {} has an edge to itself", v),
+            GraphError::InvalidPieces => {
+                write!(f, "pieces are not a permutation of 0..vertices")
+            }
+            GraphError::IllegalSlide(v) => {
+                write!(f, "vertex {} is not next to the empty vertex", v)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+/* Pieces on the vertices of a graph, one vertex empty */
+#[derive(Debug, Clone)]
+pub struct GraphPuzzle {
+    adj: Vec<Vec<usize>>, // neighbours of each vertex, each once
+    pieces: Vec<u32>,     // 0 marks the empty vertex
+    empty: usize,
+}
+
+impl GraphPuzzle {
+    pub fn new(
+        vertices: usize,
+        edges: &[(usize, usize)],
+        pieces: Vec<u32>,
+    ) -> Result<Self, GraphError> {
+        if vertices == 0 {
+            return Err(GraphError::EmptyGraph);
+        }
+        let mut adj = vec![Vec::new(); vertices];
+        for &(from, to) in edges {
+            if from >= vertices || to >= vertices {
+                return Err(GraphError::EdgeOutOfRange { from, to });
+            }
+            if from == to {
+                return Err(GraphError::SelfLoop(from));
+            }
+            if !adj[from].contains(&to) {
+                adj[from].push(to);
+                adj[to].push(from);
+            }
+        }
+        Self::from_adjacency(adj, pieces)
+    }
+
+    // Solved graph, every piece home and the last vertex empty
+    pub fn goal(vertices: usize, edges: &[(usize, usize)]) -> Result<Self, GraphError> {
+        let pieces = (1..vertices as u32).chain([0]).collect();
+        Self::new(vertices, edges, pieces)
+    }
+
+    fn from_adjacency(adj: Vec<Vec<usize>>, pieces: Vec<u32>) -> Result<Self, GraphError> {
+        if pieces.len() != adj.len() {
+            return Err(GraphError::InvalidPieces);
+        }
+        let mut seen = vec![false; adj.len()];
+        for &piece in &pieces {
+            if piece as usize >= adj.len() || seen[piece as usize] {
+                return Err(GraphError::InvalidPieces);
+            }
+            seen[piece as usize] = true;
+        }
+        let empty = pieces.iter().position(|&p| p == 0).unwrap_or(0);
+        Ok(Self { adj, pieces, empty })
+    }
+
+    pub fn vertices(&self) -> usize {
+        self.adj.len()
+    }
+
+    pub fn neighbours(&self, v: usize) -> &[usize] {
+        &self.adj[v]
+    }
+
+    // piece on each vertex, 0 is the empty vertex
+    pub fn pieces(&self) -> &[u32] {
+        &self.pieces
+    }
+
+    pub fn empty(&self) -> usize {
+        self.empty
+    }
+
+    pub fn is_solved(&self) -> bool {
+        in_goal(&self.pieces)
+    }
+
+    // Slide the piece on `from` into the empty vertex next to it
+    pub fn slide(&mut self, from: usize) -> Result<(), GraphError> {
+        if !self.adj[self.empty].contains(&from) {
+            return Err(GraphError::IllegalSlide(from));
+        }
+        self.pieces.swap(from, self.empty);
+        self.empty = from;
+        Ok(())
+    }
+
+    pub fn is_solvable(&self) -> bool {
+        let n = self.vertices();
+        let goal_empty = n - 1;
+        let alive = vec![true; n];
+        // vertices the empty one can reach, numbered breadth first from it
+        let (cells, adj) = self.subgraph(&alive, self.empty);
+        let mut local = vec![usize::MAX; n];
+        for (i, &v) in cells.iter().enumerate() {
+            local[v] = i;
+        }
+        if local[goal_empty] == usize::MAX {
+            return false;
+        }
+        let frozen_home =
+            (0..n).all(|v| local[v] != usize::MAX || self.pieces[v] as usize == v + 1);
+        if !frozen_home {
+            return false;
+        }
+        // where the piece on each reachable vertex belongs, the empty one included
+        let target: Vec<usize> = cells
+            .iter()
+            .map(|&v| match self.pieces[v] {
+                0 => local[goal_empty],
+                piece => local[piece as usize - 1],
+            })
+            .collect();
+        let goal = local[goal_empty];
+        let parity_holds =
+            |colour: &[bool]| odd_permutation(&target) == (colour[0] != colour[goal]);
+        let reachable = |p: &Self| {
+            let inside: Vec<bool> = local.iter().map(|&i| i != usize::MAX).collect();
+            let mut opts = SolveOptions::default();
+            let mut meter = Meter::new(&mut opts);
+            matches!(p.search(&inside, 0, usize::MAX, &mut meter), Ok(Some(_)))
+        };
+        match shape(&adj) {
+            Shape::Path(order) => keeps_order(&order, &target, goal, false),
+            Shape::Cycle(order) => keeps_order(&order, &target, goal, true),
+            Shape::Biconnected => match two_colouring(&adj) {
+                Some(colour) => parity_holds(&colour),
+                None => !is_theta_zero(&adj) || reachable(self),
+            },
+            Shape::Other => blocks_allow(&adj, &target, goal),
+        }
+    }
+
+    // Solve, each move as (vertex the piece left, vertex it entered)
+    pub fn solve(&mut self) -> Result<Vec<(usize, usize)>, SolveError> {
+        self.solve_with(&mut SolveOptions::default())
+    }
+
+    // Solve, stopping early as the options ask; nodes count the states the
+    // searches expand
+    pub fn solve_with(
+        &mut self,
+        opts: &mut SolveOptions,
+    ) -> Result<Vec<(usize, usize)>, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        let mut meter = Meter::new(opts);
+        let mut alive = vec![false; self.vertices()];
+        let (cells, adj) = self.subgraph(&vec![true; self.vertices()], self.empty);
+        for &v in &cells {
+            alive[v] = true;
+        }
+        let mut moves = Vec::new();
+        let mut left = cells.len();
+        if matches!(shape(&adj), Shape::Biconnected) {
+            let odd = two_colouring(&adj).is_none();
+            for unit in self.plan(&alive, left, odd) {
+                meter.checkpoint()?;
+                if let [v] = unit[..] {
+                    let from = self.position(v);
+                    self.carry(from, v, &alive, &mut moves, &mut meter)?;
+                } else {
+                    self.fill_ear(&unit, &alive, &mut moves, &mut meter)?;
+                }
+                for &v in &unit {
+                    alive[v] = false;
+                }
+                left -= unit.len();
+            }
+        }
+        if left > FINISH_VERTICES && matches!(shape(&adj), Shape::Biconnected) {
+            self.cycle_home(&alive, &mut moves, &mut meter)?;
+            return Ok(moves);
+        }
+        let weight = if left > FINISH_VERTICES {
+            FINISH_WEIGHT
+        } else {
+            0
+        };
+        let rest = self
+            .search(&alive, weight, MAX_FINISH_STATES, &mut meter)?
+            .ok_or(SolveError::SearchExhausted)?;
+        for from in rest {
+            self.play(from, &mut moves);
+        }
+        Ok(moves)
+    }
+
+    fn play(&mut self, from: usize, moves: &mut Vec<(usize, usize)>) {
+        let to = self.empty;
+        if self.slide(from).is_ok() {
+            moves.push((from, to));
+        }
+    }
+
+    // vertex the piece belonging on v is on
+    fn position(&self, v: usize) -> usize {
+        let piece = v as u32 + 1;
+        self.pieces.iter().position(|&p| p == piece).unwrap_or(v)
+    }
+
+    // Vertices and ears to peel, in order, down to FINISH_VERTICES; greedy
+    // peeling can strand a long cycle with one ear across, so this backtracks
+    // over the shape alone and settles for the smallest rest it saw
+    fn plan(&self, alive: &[bool], left: usize, odd: bool) -> Vec<Vec<usize>> {
+        let mut plan = Plan {
+            odd,
+            path: Vec::new(),
+            best: Vec::new(),
+            best_left: left,
+            seen: HashSet::new(),
+            nodes: 0,
+        };
+        plan.visit(self, &mut alive.to_vec(), left);
+        plan.best
+    }
+
+    // every vertex, and every ear (a path of degree-2 vertices, like the last
+    // two of a row on a grid), whose removal leaves the rest 2-connected, not
+    // theta-0, and not two-coloured if it had an odd cycle; furthest from the
+    // empty vertex's home first
+    fn units(&self, alive: &[bool], left: usize, odd: bool) -> Vec<Vec<usize>> {
+        let goal_empty = self.vertices() - 1;
+        let (mut order, _) = self.subgraph(alive, goal_empty);
+        order.reverse();
+        let mut rest = alive.to_vec();
+        let mut keeps_rest = |unit: &[usize]| {
+            for &v in unit {
+                rest[v] = false;
+            }
+            let (cells, adj) = self.subgraph(&rest, goal_empty);
+            for &v in unit {
+                rest[v] = true;
+            }
+            cells.len() == left - unit.len()
+                && matches!(shape(&adj), Shape::Biconnected)
+                && !is_theta_zero(&adj)
+                && (!odd || two_colouring(&adj).is_none())
+        };
+        let next = |v: usize, prev: usize| {
+            let mut around = self.adj[v].iter().copied().filter(|&w| alive[w]);
+            match (around.next(), around.next(), around.next()) {
+                (Some(a), Some(b), None) => Some(if a == prev { b } else { a }),
+                _ => None,
+            }
+        };
+        let mut units: Vec<Vec<usize>> = Vec::new();
+        for &v in order.iter().filter(|&&v| v != goal_empty) {
+            if keeps_rest(&[v]) {
+                units.push(vec![v]);
+                continue;
+            }
+            // the whole run of degree-2 vertices through v, end to end
+            let (Some(a), Some(b)) = (
+                next(v, usize::MAX),
+                next(v, usize::MAX).and_then(|a| next(v, a)),
+            ) else {
+                continue;
+            };
+            let run = |mut prev: usize, mut at: usize| {
+                let mut run = Vec::new();
+                while at != goal_empty && at != v {
+                    let Some(after) = next(at, prev) else {
+                        break;
+                    };
+                    run.push(at);
+                    (prev, at) = (at, after);
+                }
+                run
+            };
+            let mut ear = run(v, a);
+            ear.reverse();
+            ear.push(v);
+            ear.extend(run(v, b));
+            // filling it needs room for all its own pieces outside at once
+            let roomy = 2 * ear.len() + 2 <= left;
+            if ear.len() > 1 && roomy && !units.iter().any(|u| u.contains(&v)) && keeps_rest(&ear) {
+                units.push(ear);
+            }
+        }
+        units
+    }
+
+    // Fill an ear as a queue: a piece pushed in at one end, x, shoves the
+    // others along and the last one out at the other end, y; first push in
+    // other pieces until none of the ear's own is left inside, then its own
+    // pieces, furthest first
+    fn fill_ear(
+        &mut self,
+        ear: &[usize],
+        alive: &[bool],
+        moves: &mut Vec<(usize, usize)>,
+        meter: &mut Meter,
+    ) -> Result<(), SolveError> {
+        let k = ear.len();
+        let beyond = |end: usize, inner: usize| {
+            let mut around = self.adj[end].iter().copied();
+            around.find(|&w| alive[w] && w != inner)
+        };
+        let (Some(x), Some(y)) = (beyond(ear[0], ear[1]), beyond(ear[k - 1], ear[k - 2])) else {
+            return Err(SolveError::SearchExhausted);
+        };
+        let mut rest = alive.to_vec();
+        for &u in ear {
+            rest[u] = false;
+        }
+        // the empty vertex leaves the ear through x
+        if let Some(i) = ear.iter().position(|&u| u == self.empty) {
+            for &u in ear[..i].iter().rev() {
+                self.play(u, moves);
+            }
+            self.play(x, moves);
+        }
+        let own = |p: u32| p != 0 && ear.contains(&(p as usize - 1));
+        while ear.iter().any(|&u| own(self.pieces[u])) {
+            // nearest piece to x that belongs elsewhere
+            let (near, _) = self.subgraph(&rest, x);
+            let other = near
+                .into_iter()
+                .find(|&w| self.pieces[w] != 0 && !own(self.pieces[w]));
+            let from = other.ok_or(SolveError::SearchExhausted)?;
+            self.push(from, (x, y), ear, &rest, moves, meter)?;
+        }
+        for &u in ear.iter().rev() {
+            let from = self.position(u);
+            self.push(from, (x, y), ear, &rest, moves, meter)?;
+        }
+        Ok(())
+    }
+
+    // carry the piece on from to x without entering the ear, go round to y
+    // and walk the empty vertex back through the ear, pulling the piece in
+    fn push(
+        &mut self,
+        from: usize,
+        (x, y): (usize, usize),
+        ear: &[usize],
+        rest: &[bool],
+        moves: &mut Vec<(usize, usize)>,
+        meter: &mut Meter,
+    ) -> Result<(), SolveError> {
+        self.carry(from, x, rest, moves, meter)?;
+        let around = self
+            .route(rest, self.empty, y, x)
+            .ok_or(SolveError::SearchExhausted)?;
+        for &w in &around[1..] {
+            self.play(w, moves);
+        }
+        for &u in ear.iter().rev() {
+            self.play(u, moves);
+        }
+        self.play(x, moves);
+        Ok(())
+    }
+
+    // Bring the piece on from to `to` through alive vertices, breadth first
+    // over where it and the empty vertex are
+    fn carry(
+        &mut self,
+        from: usize,
+        to: usize,
+        alive: &[bool],
+        moves: &mut Vec<(usize, usize)>,
+        meter: &mut Meter,
+    ) -> Result<(), SolveError> {
+        let start = (from, self.empty);
+        let mut prev = HashMap::from([(start, (start, 0))]);
+        let mut queue = VecDeque::from([start]);
+        while let Some((piece, empty)) = queue.pop_front() {
+            meter.tick()?;
+            if piece == to {
+                let mut lines = Vec::new();
+                let mut at = (piece, empty);
+                while at != start {
+                    lines.push(prev[&at].1);
+                    at = prev[&at].0;
+                }
+                for &line in lines.iter().rev() {
+                    self.play(line, moves);
+                }
+                return Ok(());
+            }
+            for &w in &self.adj[empty] {
+                let next = (if w == piece { empty } else { piece }, w);
+                if alive[w] && !prev.contains_key(&next) {
+                    prev.insert(next, ((piece, empty), w));
+                    queue.push_back(next);
+                }
+            }
+        }
+        Err(SolveError::SearchExhausted)
+    }
+
+    // shortest path through alive vertices other than avoid, both ends included
+    fn route(&self, alive: &[bool], from: usize, to: usize, avoid: usize) -> Option<Vec<usize>> {
+        let mut prev = vec![usize::MAX; self.vertices()];
+        prev[from] = from;
+        let mut queue = VecDeque::from([from]);
+        while let Some(v) = queue.pop_front() {
+            if v == to {
+                let mut path = vec![to];
+                while path[path.len() - 1] != from {
+                    path.push(prev[path[path.len() - 1]]);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &w in &self.adj[v] {
+                if alive[w] && w != avoid && prev[w] == usize::MAX {
+                    prev[w] = v;
+                    queue.push_back(w);
+                }
+            }
+        }
+        None
+    }
+
+    // alive vertices reachable from start, breadth first, with adjacency
+    // lists renumbered in that order
+    fn subgraph(&self, alive: &[bool], start: usize) -> (Vec<usize>, Vec<Vec<usize>>) {
+        let mut cells = vec![start];
+        let mut local = vec![usize::MAX; self.vertices()];
+        local[start] = 0;
+        let mut adj = Vec::new();
+        while let Some(&v) = cells.get(adj.len()) {
+            let mut next = Vec::new();
+            for &w in &self.adj[v] {
+                if !alive[w] {
+                    continue;
+                }
+                if local[w] == usize::MAX {
+                    local[w] = cells.len();
+                    cells.push(w);
+                }
+                next.push(local[w]);
+            }
+            adj.push(next);
+        }
+        (cells, adj)
+    }
+
+    // Finish a 2-connected rest too big to search: with the empty vertex
+    // home, a trip round an odd cycle if the permutation is odd, then one
+    // 3-cycle per piece, conjugated so it takes the piece home and leaves the
+    // pieces already home alone
+    fn cycle_home(
+        &mut self,
+        alive: &[bool],
+        moves: &mut Vec<(usize, usize)>,
+        meter: &mut Meter,
+    ) -> Result<(), SolveError> {
+        let home = self.vertices() - 1;
+        let path = self
+            .route(alive, self.empty, home, usize::MAX)
+            .ok_or(SolveError::SearchExhausted)?;
+        for &w in &path[1..] {
+            self.play(w, moves);
+        }
+        let target: Vec<usize> = (0..self.vertices())
+            .map(|v| match self.pieces[v] {
+                0 => v,
+                piece => piece as usize - 1,
+            })
+            .collect();
+        if odd_permutation(&target) {
+            let walk = self
+                .odd_walk(alive, home)
+                .ok_or(SolveError::SearchExhausted)?;
+            for w in walk {
+                self.play(w, moves);
+            }
+        }
+        let (turn, spots) = self
+            .three_cycle(alive, home)
+            .ok_or(SolveError::SearchExhausted)?;
+        let (mut order, _) = self.subgraph(alive, home);
+        order.reverse();
+        order.pop();
+        let mut done = vec![false; self.vertices()];
+        for &v in &order {
+            if self.pieces[v] as usize != v + 1 {
+                meter.checkpoint()?;
+                let u = self.position(v);
+                let w = order
+                    .iter()
+                    .copied()
+                    .find(|&w| w != u && w != v && !done[w]);
+                let w = w.ok_or(SolveError::SearchExhausted)?;
+                let setup = self
+                    .setup(alive, [u, v, w], spots, home, meter)?
+                    .ok_or(SolveError::SearchExhausted)?;
+                let back = reversed(home, &setup);
+                for &x in setup.iter().chain(&turn).chain(&back) {
+                    self.play(x, moves);
+                }
+            }
+            done[v] = true;
+        }
+        Ok(())
+    }
+
+    // closed walk of odd length from home: up the breadth first tree to one end
+    // of an edge joining two vertices of equal depth, across, and back down
+    fn odd_walk(&self, alive: &[bool], home: usize) -> Option<Vec<usize>> {
+        let (cells, _) = self.subgraph(alive, home);
+        let mut depth = vec![usize::MAX; self.vertices()];
+        depth[home] = 0;
+        let mut prev = vec![home; self.vertices()];
+        for &v in &cells {
+            for &w in &self.adj[v] {
+                if alive[w] && depth[w] == usize::MAX {
+                    depth[w] = depth[v] + 1;
+                    prev[w] = v;
+                }
+            }
+        }
+        let (u, w) = cells.iter().find_map(|&u| {
+            let w = self.adj[u]
+                .iter()
+                .find(|&&w| alive[w] && depth[w] == depth[u]);
+            w.map(|&w| (u, w))
+        })?;
+        let down = |mut v: usize| {
+            let mut path = Vec::new();
+            while v != home {
+                path.push(v);
+                v = prev[v];
+            }
+            path
+        };
+        let mut walk = down(u);
+        walk.reverse();
+        walk.extend(down(w));
+        walk.push(home);
+        Some(walk)
+    }
+
+    // Short closed walk from home that moves exactly three pieces, with the
+    // vertices (p1, p2, p3) it sends p1 -> p2 -> p3 -> p1: a commutator of two
+    // turns round cycles, or else of two such commutators whose moved pieces
+    // have one vertex in common (any two permutations moving one point in
+    // common commute to a 3-cycle)
+    fn three_cycle(&self, alive: &[bool], home: usize) -> Option<(Vec<usize>, [usize; 3])> {
+        let mut turns = Vec::new();
+        for x in (0..self.vertices()).filter(|&x| alive[x]) {
+            let around: Vec<usize> = self.adj[x].iter().copied().filter(|&w| alive[w]).collect();
+            if around.len() < 3 {
+                continue;
+            }
+            let Some(to_x) = self.route(alive, home, x, usize::MAX) else {
+                continue;
+            };
+            let to_x = &to_x[1..];
+            let from_x = reversed(home, to_x);
+            for (i, &a) in around.iter().enumerate() {
+                for &b in &around[i + 1..] {
+                    let Some(mut cycle) = self.route(alive, a, b, x) else {
+                        continue;
+                    };
+                    cycle.push(x);
+                    for cycle in [reversed(x, &cycle), cycle] {
+                        let walk: Vec<usize> = [to_x, &cycle, &from_x].concat();
+                        turns.push(walk);
+                    }
+                }
+            }
+        }
+        let mut best: Option<Vec<usize>> = None;
+        let mut small = Vec::new();
+        for (i, one) in turns.iter().enumerate() {
+            for two in &turns[i + 1..] {
+                let walk = commutator(home, one, two);
+                let moved = self.moved(home, &walk);
+                if moved.len() == 3 {
+                    if best.as_ref().is_none_or(|b| walk.len() < b.len()) {
+                        best = Some(walk);
+                    }
+                } else if moved.len() > 3 && moved.len() <= MAX_MOVED {
+                    small.push((walk, moved));
+                }
+            }
+        }
+        small.sort_by_key(|(walk, _)| walk.len());
+        small.truncate(MAX_COMMUTATORS);
+        for (i, (one, moved_one)) in small.iter().enumerate() {
+            for (two, moved_two) in &small[i + 1..] {
+                let len = 2 * (one.len() + two.len());
+                if best.as_ref().is_some_and(|b| b.len() <= len) {
+                    break;
+                }
+                let common = moved_one.iter().filter(|v| moved_two.contains(v)).count();
+                if common == 1 {
+                    best = Some(commutator(home, one, two));
+                }
+            }
+        }
+        let walk = best?;
+        let moved = self.moved(home, &walk);
+        let came_from = self.came_from(home, &walk);
+        let p1 = moved[0];
+        let p3 = came_from[p1];
+        let p2 = came_from[p3];
+        Some((walk, [p1, p2, p3]))
+    }
+
+    // for each vertex, the vertex its piece was on before a walk of the empty
+    // vertex from home
+    fn came_from(&self, home: usize, walk: &[usize]) -> Vec<usize> {
+        let mut came_from: Vec<usize> = (0..self.vertices()).collect();
+        let mut empty = home;
+        for &w in walk {
+            came_from.swap(w, empty);
+            empty = w;
+        }
+        came_from
+    }
+
+    // vertices whose piece a closed walk from home moves
+    fn moved(&self, home: usize, walk: &[usize]) -> Vec<usize> {
+        let came_from = self.came_from(home, walk);
+        (0..self.vertices())
+            .filter(|&v| came_from[v] != v)
+            .collect()
+    }
+
+    // walk from home after which the pieces on from sit on to, in order, and
+    // the empty vertex is home again; breadth first over where those three
+    // pieces and the empty vertex are
+    fn setup(
+        &self,
+        alive: &[bool],
+        from: [usize; 3],
+        to: [usize; 3],
+        home: usize,
+        meter: &mut Meter,
+    ) -> Result<Option<Vec<usize>>, SolveError> {
+        let start = (from, home);
+        let mut prev = HashMap::from([(start, (start, 0))]);
+        let mut queue = VecDeque::from([start]);
+        while let Some((at, empty)) = queue.pop_front() {
+            meter.tick()?;
+            if (at, empty) == (to, home) {
+                let mut walk = Vec::new();
+                let mut state = (at, empty);
+                while state != start {
+                    walk.push(prev[&state].1);
+                    state = prev[&state].0;
+                }
+                walk.reverse();
+                return Ok(Some(walk));
+            }
+            for &w in self.adj[empty].iter().filter(|&&w| alive[w]) {
+                let next = at.map(|p| if p == w { empty } else { p });
+                if let Entry::Vacant(slot) = prev.entry((next, w)) {
+                    slot.insert(((at, empty), w));
+                    queue.push_back((next, w));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // best first to the goal sliding only pieces on alive vertices, f = g + weight * h
+    // with h the pieces' distances home, deeper first on ties; weight 0 is
+    // breadth first, so shortest
+    fn search(
+        &self,
+        alive: &[bool],
+        weight: u32,
+        max_states: usize,
+        meter: &mut Meter,
+    ) -> Result<Option<Vec<usize>>, SolveError> {
+        let dist = if weight == 0 {
+            Vec::new()
+        } else {
+            self.distances(alive)
+        };
+        let h = |pieces: &[u32]| -> u32 {
+            if weight == 0 {
+                return 0;
+            }
+            let placed = pieces
+                .iter()
+                .enumerate()
+                .filter(|&(v, &p)| alive[v] && p != 0);
+            placed.map(|(v, &p)| dist[v][p as usize - 1]).sum()
+        };
+        let mut seen = HashSet::from([self.pieces.clone()]);
+        let mut states = vec![(self.pieces.clone(), self.empty)];
+        let mut parent = vec![(usize::MAX, 0)];
+        let mut open = BinaryHeap::from([Reverse((weight * h(&self.pieces), Reverse(0), 0))]);
+        while let Some(Reverse((_, Reverse(g), mut i))) = open.pop() {
+            meter.tick()?;
+            let (pieces, empty) = states[i].clone();
+            if in_goal(&pieces) {
+                let mut lines = Vec::new();
+                while parent[i].0 != usize::MAX {
+                    lines.push(parent[i].1);
+                    i = parent[i].0;
+                }
+                lines.reverse();
+                return Ok(Some(lines));
+            }
+            for &from in &self.adj[empty] {
+                if !alive[from] {
+                    continue;
+                }
+                let mut next = pieces.clone();
+                next.swap(from, empty);
+                if seen.insert(next.clone()) {
+                    if states.len() >= max_states {
+                        return Ok(None);
+                    }
+                    let f = g + 1 + weight * h(&next);
+                    open.push(Reverse((f, Reverse(g + 1), states.len())));
+                    states.push((next, from));
+                    parent.push((i, from));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // edges between every two alive vertices, through alive vertices
+    fn distances(&self, alive: &[bool]) -> Vec<Vec<u32>> {
+        let n = self.vertices();
+        let mut dist = vec![vec![u32::MAX; n]; n];
+        for (from, row) in dist.iter_mut().enumerate() {
+            if !alive[from] {
+                continue;
+            }
+            row[from] = 0;
+            let mut queue = VecDeque::from([from]);
+            while let Some(v) = queue.pop_front() {
+                for &w in &self.adj[v] {
+                    if alive[w] && row[w] == u32::MAX {
+                        row[w] = row[v] + 1;
+                        queue.push_back(w);
+                    }
+                }
+            }
+        }
+        dist
+    }
+}
+
+/* Options of one solve and the states its searches have expanded so far */
+struct Meter<'o, 'a> {
+    opts: &'o mut SolveOptions<'a>,
+    nodes: u64,
+}
+
+impl<'o, 'a> Meter<'o, 'a> {
+    fn new(opts: &'o mut SolveOptions<'a>) -> Self {
+        Self { opts, nodes: 0 }
+    }
+
+    // one more state expanded
+    fn tick(&mut self) -> Result<(), SolveError> {
+        self.nodes += 1;
+        self.opts.tick(self.nodes, None)
+    }
+
+    fn checkpoint(&mut self) -> Result<(), SolveError> {
+        self.opts.checkpoint(Progress::nodes(self.nodes, None))
+    }
+}
+
+/* Depth first search for a peeling order */
+struct Plan {
+    odd: bool, // the component had an odd cycle
+    path: Vec<Vec<usize>>,
+    best: Vec<Vec<usize>>,
+    best_left: usize,
+    seen: HashSet<Vec<bool>>,
+    nodes: usize,
+}
+
+impl Plan {
+    // true once the rest is small enough to search
+    fn visit(&mut self, graph: &GraphPuzzle, alive: &mut [bool], left: usize) -> bool {
+        if left < self.best_left {
+            self.best_left = left;
+            self.best = self.path.clone();
+        }
+        if left <= FINISH_VERTICES {
+            return true;
+        }
+        if self.nodes >= MAX_PLAN_NODES || !self.seen.insert(alive.to_vec()) {
+            return false;
+        }
+        self.nodes += 1;
+        for unit in graph.units(alive, left, self.odd) {
+            for &v in &unit {
+                alive[v] = false;
+            }
+            let size = unit.len();
+            self.path.push(unit);
+            let done = self.visit(graph, alive, left - size);
+            let unit = self.path.pop().unwrap_or_default();
+            for &v in &unit {
+                alive[v] = true;
+            }
+            if done {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl Puzzle {
+    // Same board as a graph, vertex i is the i-th free cell in reading order
+    pub fn to_graph(&self) -> GraphPuzzle {
+        let free: Vec<usize> = (0..self.grid.len())
+            .filter(|&idx| self.grid[idx] != WALL)
+            .collect();
+        let mut vertex = vec![usize::MAX; self.grid.len()];
+        for (v, &idx) in free.iter().enumerate() {
+            vertex[idx] = v;
+        }
+        let adj = free
+            .iter()
+            .map(|&idx| {
+                let next = self.free_neighbours(idx);
+                next.into_iter().map(|n| vertex[n]).collect()
+            })
+            .collect();
+        let pieces = free
+            .iter()
+            .map(|&idx| match self.grid[idx] {
+                0 => 0,
+                tile => vertex[tile as usize - 1] as u32 + 1,
+            })
+            .collect();
+        let empty = vertex[self.index(self.blank.0, self.blank.1)];
+        GraphPuzzle { adj, pieces, empty }
+    }
+}
+
+// one^-1 two^-1 one two as walks from home, played left to right
+fn commutator(home: usize, one: &[usize], two: &[usize]) -> Vec<usize> {
+    [&reversed(home, one), &reversed(home, two), one, two].concat()
+}
+
+// the walk back: the vertices of start, walk... in reverse, but the first
+fn reversed(start: usize, walk: &[usize]) -> Vec<usize> {
+    let mut back: Vec<usize> = walk.iter().rev().skip(1).copied().collect();
+    if !walk.is_empty() {
+        back.push(start);
+    }
+    back
+}
+
+// every piece home, the last vertex empty
+fn in_goal(pieces: &[u32]) -> bool {
+    let n = pieces.len();
+    pieces
+        .iter()
+        .enumerate()
+        .all(|(v, &p)| p as usize == (v + 1) % n)
+}
+
+// a hexagon with a path of length 2 between opposite corners: two vertices of
+// degree 3 joined by paths of 2, 3 and 3 edges (asked of 2-connected graphs)
+fn is_theta_zero(adj: &[Vec<usize>]) -> bool {
+    let edges = adj.iter().map(Vec::len).sum::<usize>() / 2;
+    let hubs: Vec<usize> = (0..adj.len()).filter(|&v| adj[v].len() == 3).collect();
+    if adj.len() != 7 || edges != 8 || hubs.len() != 2 {
+        return false;
+    }
+    let mut lengths: Vec<usize> = adj[hubs[0]]
+        .iter()
+        .map(|&first| {
+            let (mut prev, mut v, mut len) = (hubs[0], first, 1);
+            while adj[v].len() == 2 {
+                let next = if adj[v][0] == prev {
+                    adj[v][1]
+                } else {
+                    adj[v][0]
+                };
+                (prev, v, len) = (v, next, len + 1);
+            }
+            len
+        })
+        .collect();
+    lengths.sort();
+    lengths == [2, 3, 3]
+}
+
+/* What a connected graph looks like, as far as moving pebbles on it goes */
+enum Shape {
+    Path(Vec<usize>),  // vertices end to end
+    Cycle(Vec<usize>), // vertices round the ring
+    Biconnected,       // no cut vertex, not a cycle
+    Other,
+}
+
+// shape of a connected graph given as adjacency lists
+fn shape(adj: &[Vec<usize>]) -> Shape {
+    let n = adj.len();
+    let edges = adj.iter().map(Vec::len).sum::<usize>() / 2;
+    if adj.iter().all(|next| next.len() <= 2) && edges <= n {
+        // walk from an end (or anywhere on a ring)
+        let start = (0..n).find(|&v| adj[v].len() < 2).unwrap_or(0);
+        let mut order = vec![start];
+        let mut prev = usize::MAX;
+        while let Some(&next) = adj[order[order.len() - 1]]
+            .iter()
+            .find(|&&v| v != prev && v != start)
+        {
+            prev = order[order.len() - 1];
+            order.push(next);
+        }
+        return if edges == n && n > 2 {
+            Shape::Cycle(order)
+        } else {
+            Shape::Path(order)
+        };
+    }
+    if has_cut_vertex(adj) {
+        Shape::Other
+    } else {
+        Shape::Biconnected
+    }
+}
+
+// Tarjan's low-link test from vertex 0
+fn has_cut_vertex(adj: &[Vec<usize>]) -> bool {
+    fn visit(
+        adj: &[Vec<usize>],
+        v: usize,
+        parent: usize,
+        depth: &mut [usize],
+        low: &mut [usize],
+    ) -> bool {
+        let mut children = 0;
+        let mut cut = false;
+        for &w in &adj[v] {
+            if depth[w] == usize::MAX {
+                depth[w] = depth[v] + 1;
+                low[w] = depth[w];
+                children += 1;
+                cut |= visit(adj, w, v, depth, low);
+                low[v] = low[v].min(low[w]);
+                if parent != usize::MAX && low[w] >= depth[v] {
+                    cut = true;
+                }
+            } else if w != parent {
+                low[v] = low[v].min(depth[w]);
+            }
+        }
+        cut || (parent == usize::MAX && children > 1)
+    }
+    let mut depth = vec![usize::MAX; adj.len()];
+    let mut low = vec![0; adj.len()];
+    depth[0] = 0;
+    visit(adj, 0, usize::MAX, &mut depth, &mut low)
+}
+
+// Vertex sets of the blocks: maximal 2-connected subgraphs and lone edges
+fn blocks(adj: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    fn visit(
+        adj: &[Vec<usize>],
+        v: usize,
+        depth: &mut [usize],
+        low: &mut [usize],
+        stack: &mut Vec<usize>,
+        out: &mut Vec<Vec<usize>>,
+    ) {
+        stack.push(v);
+        for &w in &adj[v] {
+            if depth[w] == usize::MAX {
+                depth[w] = depth[v] + 1;
+                low[w] = depth[w];
+                visit(adj, w, depth, low, stack, out);
+                low[v] = low[v].min(low[w]);
+                // nothing below w climbs past v: v closes off a block
+                if low[w] >= depth[v] {
+                    let at = stack.iter().rposition(|&u| u == w).unwrap_or(0);
+                    let mut block = stack.split_off(at);
+                    block.push(v);
+                    out.push(block);
+                }
+            } else {
+                low[v] = low[v].min(depth[w]);
+            }
+        }
+    }
+    let mut depth = vec![usize::MAX; adj.len()];
+    let mut low = vec![0; adj.len()];
+    let mut out = Vec::new();
+    depth[0] = 0;
+    visit(adj, 0, &mut depth, &mut low, &mut Vec::new(), &mut out);
+    out
+}
+
+// Whether every block can put its pieces home (connected graph, vertex 0 holds
+// the blank, target as in keeps_order): walk the empty vertex home, then each
+// block's pieces are the ones off its vertex nearest home, and must stay there
+fn blocks_allow(adj: &[Vec<usize>], target: &[usize], goal: usize) -> bool {
+    let mut dist = vec![usize::MAX; adj.len()];
+    let mut toward = vec![goal; adj.len()];
+    dist[goal] = 0;
+    let mut queue = VecDeque::from([goal]);
+    while let Some(v) = queue.pop_front() {
+        for &w in &adj[v] {
+            if dist[w] == usize::MAX {
+                dist[w] = dist[v] + 1;
+                toward[w] = v;
+                queue.push_back(w);
+            }
+        }
+    }
+    let mut at = target.to_vec();
+    let mut empty = 0;
+    while empty != goal {
+        at.swap(empty, toward[empty]);
+        empty = toward[empty];
+    }
+    blocks(adj).into_iter().all(|block| {
+        // the blank of this block, the piece on it belongs further in
+        let base = block.iter().copied().min_by_key(|&v| dist[v]).unwrap_or(0);
+        let mut order: Vec<usize> = block.into_iter().filter(|&v| v != base).collect();
+        order.push(base);
+        let local: HashMap<usize, usize> = order.iter().enumerate().map(|(i, &v)| (v, i)).collect();
+        let mut pieces = Vec::with_capacity(order.len());
+        for &v in &order[..order.len() - 1] {
+            match local.get(&at[v]) {
+                Some(&home) if at[v] != base => pieces.push(home as u32 + 1),
+                _ => return false,
+            }
+        }
+        pieces.push(0);
+        let edges: Vec<(usize, usize)> = order
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &v)| {
+                adj[v]
+                    .iter()
+                    .filter_map(|w| local.get(w))
+                    .map(move |&j| (i, j))
+            })
+            .filter(|&(i, j)| i < j)
+            .collect();
+        GraphPuzzle::new(order.len(), &edges, pieces).is_ok_and(|g| g.is_solvable())
+    })
+}
+
+// side of each vertex in a two-colouring, None if there is an odd cycle
+fn two_colouring(adj: &[Vec<usize>]) -> Option<Vec<bool>> {
+    let mut colour = vec![None; adj.len()];
+    for start in 0..adj.len() {
+        if colour[start].is_some() {
+            continue;
+        }
+        colour[start] = Some(false);
+        let mut stack = vec![start];
+        while let Some(v) = stack.pop() {
+            let side = colour[v] == Some(true);
+            for &w in &adj[v] {
+                match colour[w] {
+                    None => {
+                        colour[w] = Some(!side);
+                        stack.push(w);
+                    }
+                    Some(other) if other == side => return None,
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+    colour.into_iter().collect()
+}
+
+// parity of the permutation v -> target[v]
+fn odd_permutation(target: &[usize]) -> bool {
+    let mut seen = vec![false; target.len()];
+    let mut cycles = 0;
+    for start in 0..target.len() {
+        if seen[start] {
+            continue;
+        }
+        cycles += 1;
+        let mut v = start;
+        while !seen[v] {
+            seen[v] = true;
+            v = target[v];
+        }
+    }
+    (target.len() - cycles) % 2 == 1
+}
+
+// pieces read along order (skipping the blank) are in goal order,
+// up to rotation on a ring; vertex 0 holds the blank, goal is its home
+fn keeps_order(order: &[usize], target: &[usize], goal: usize, ring: bool) -> bool {
+    let now: Vec<usize> = order
+        .iter()
+        .filter(|&&v| v != 0)
+        .map(|&v| target[v])
+        .collect();
+    let want: Vec<usize> = order.iter().copied().filter(|&v| v != goal).collect();
+    if !ring || want.is_empty() {
+        return now == want;
+    }
+    let shift = now.iter().position(|&v| v == want[0]).unwrap_or(0);
+    (0..want.len()).all(|i| now[(shift + i) % now.len()] == want[i])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::puzzle::tables::{factorial, unrank};
+
+    fn ring(n: usize) -> Vec<(usize, usize)> {
+        (0..n).map(|v| (v, (v + 1) % n)).collect()
+    }
+
+    // every arrangement the empty vertex can reach from the goal
+    fn reachable(goal: &GraphPuzzle) -> HashSet<Vec<u32>> {
+        let mut seen = HashSet::from([goal.pieces.clone()]);
+        let mut queue = VecDeque::from([goal.clone()]);
+        while let Some(g) = queue.pop_front() {
+            for &from in g.neighbours(g.empty) {
+                let mut next = g.clone();
+                next.slide(from).unwrap();
+                if seen.insert(next.pieces.clone()) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        seen
+    }
+
+    #[test]
+    fn test_slides() {
+        let mut g = GraphPuzzle::goal(3, &[(0, 1), (1, 2)]).unwrap();
+        assert!(g.is_solved());
+        assert_eq!(g.slide(0), Err(GraphError::IllegalSlide(0)));
+        g.slide(1).unwrap();
+        assert_eq!(g.pieces(), &[1, 0, 2]);
+        assert_eq!(
+            GraphPuzzle::new(2, &[(0, 2)], vec![1, 0]).unwrap_err(),
+            GraphError::EdgeOutOfRange { from: 0, to: 2 }
+        );
+        assert_eq!(
+            GraphPuzzle::new(2, &[(1, 1)], vec![1, 0]).unwrap_err(),
+            GraphError::SelfLoop(1)
+        );
+        assert_eq!(
+            GraphPuzzle::new(2, &[(0, 1)], vec![1, 1]).unwrap_err(),
+            GraphError::InvalidPieces
+        );
+    }
+
+    #[test]
+    fn test_solvability_rule() {
+        let theta_zero = vec![
+            (0, 1),
+            (1, 2),
+            (2, 3),
+            (3, 4),
+            (4, 5),
+            (5, 0),
+            (0, 6),
+            (6, 3),
+        ];
+        let chain = vec![(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 2), (4, 5)];
+        let fan = vec![(0, 1), (1, 2), (2, 3), (3, 0), (1, 4), (4, 5), (5, 1)];
+        let graphs = [
+            (5, ring(5)),                                              // cycle
+            (4, vec![(0, 1), (1, 2), (2, 3), (3, 0), (0, 2)]),         // odd cycles
+            (5, vec![(0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4)]), // K2,3
+            (5, vec![(0, 1), (1, 2), (2, 0), (2, 3), (3, 4)]),         // cut vertex
+            (6, vec![(0, 1), (1, 2), (3, 4), (4, 5)]),                 // two paths
+            (6, chain),                                                // blocks in a row
+            (6, fan),                                                  // blocks at one vertex
+            (7, theta_zero.clone()),
+        ];
+        for (n, edges) in graphs {
+            let goal = GraphPuzzle::goal(n, &edges).unwrap();
+            let reachable = reachable(&goal);
+            // theta-0 searches every unreachable arrangement, so only a sample
+            let stride = if n == 7 { 11 } else { 1 };
+            for r in (0..factorial(n)).step_by(stride) {
+                let g = GraphPuzzle::new(n, &edges, unrank(r, n)).unwrap();
+                assert_eq!(
+                    g.is_solvable(),
+                    reachable.contains(&g.pieces),
+                    "{:?} {:?}",
+                    edges,
+                    g.pieces
+                );
+            }
+        }
+        // a 9-cycle with a tail keeps the cyclic order of its pieces
+        let mut tail = ring(9);
+        tail.push((0, 9));
+        let goal = GraphPuzzle::goal(10, &tail).unwrap();
+        assert_eq!(reachable(&goal).len(), 80);
+        let mut swapped = goal.clone();
+        swapped.pieces.swap(0, 1);
+        assert!(!swapped.is_solvable());
+        assert_eq!(swapped.solve(), Err(SolveError::Unsolvable));
+        let mut walked = goal.clone();
+        for v in [0, 1, 2, 3, 4, 5, 6, 7, 8, 0, 9] {
+            walked.slide(v).unwrap();
+        }
+        assert!(walked.is_solvable());
+        // theta-0 reaches 120 of the 720 arrangements per empty vertex
+        let goal = GraphPuzzle::goal(7, &theta_zero).unwrap();
+        assert_eq!(reachable(&goal).len(), 7 * 120);
+    }
+
+    #[test]
+    fn test_solves_boards() {
+        let boards = [
+            Puzzle::scrambled(4, 4, Scramble::Uniform, 3).unwrap(),
+            Puzzle::scrambled(3, 5, Scramble::Uniform, 4).unwrap(),
+            Puzzle::goal(3, 3)
+                .unwrap()
+                .with_topology(Topology::Torus)
+                .walked(100, 5),
+            Puzzle::with_walls(4, 5, &[(1, 1), (2, 3)])
+                .unwrap()
+                .walked(300, 6),
+            // the empty vertex's home is on a 12-cycle, 13 vertices never peel
+            Puzzle::with_walls(4, 6, &[(1, 3), (2, 4)])
+                .unwrap()
+                .walked(500, 3),
+        ];
+        for p in boards {
+            let start = p.to_graph();
+            let mut g = start.clone();
+            let moves = g.solve().unwrap();
+            assert!(g.is_solved());
+            let mut replay = start;
+            for (from, to) in moves {
+                assert_eq!(replay.empty(), to);
+                replay.slide(from).unwrap();
+            }
+            assert!(replay.is_solved());
+        }
+        // one swap on a two-coloured grid
+        let mut g = Puzzle::goal(3, 4).unwrap().to_graph();
+        g.pieces.swap(0, 1);
+        assert_eq!(g.solve(), Err(SolveError::Unsolvable));
+    }
+}
@@ -0,0 +1,430 @@
+/*
+ * heuristic.rs
+ * Estimates of how far a board is from solved
+ * every Heuristic here is admissible: never more than the optimal distance
+ *   - misplaced tiles, Manhattan distance
+ *   - linear conflict: tiles in their goal line but in the wrong order
+ *   - corner tiles and last move on top of linear conflict
+ *   - walking distance: tables of row / column moves, one BFS per board size
+ *   - MaxOf: the largest of several estimates
+ */
+
+use std::collections::HashMap;
+
+use crate::puzzle::*;
+
+// walking distance tables grow quickly, no side longer than this
+pub const MAX_WALKING_SIDE: usize = 4;
+
+/* Lower bound on the number of moves to the goal */
+pub trait Heuristic {
+    fn name(&self) -> &'static str;
+    fn estimate(&self, p: &Puzzle) -> u32;
+}
+
+pub struct MisplacedTiles;
+pub struct Manhattan;
+pub struct LinearConflict;
+pub struct CornerLastMove;
+
+impl Heuristic for MisplacedTiles {
+    fn name(&self) -> &'static str {
+        "misplaced"
+    }
+
+    fn estimate(&self, p: &Puzzle) -> u32 {
+        let tiles = p.grid.iter().enumerate();
+        tiles
+            .filter(|&(idx, &tile)| tile != 0 && tile != WALL && tile as usize != idx + 1)
+            .count() as u32
+    }
+}
+
+impl Heuristic for Manhattan {
+    fn name(&self) -> &'static str {
+        "manhattan"
+    }
+
+    fn estimate(&self, p: &Puzzle) -> u32 {
+        p.manhattan()
+    }
+}
+
+impl Heuristic for LinearConflict {
+    fn name(&self) -> &'static str {
+        "linear-conflict"
+    }
+
+    // line conflicts can be dodged round the back of a torus
+    fn estimate(&self, p: &Puzzle) -> u32 {
+        if p.topology == Topology::Torus {
+            return p.manhattan();
+        }
+        p.manhattan() + 2 * Conflicts::new(p).extra
+    }
+}
+
+impl Heuristic for CornerLastMove {
+    fn name(&self) -> &'static str {
+        "corner-last-move"
+    }
+
+    // only tiles outside every line conflict earn extra moves,
+    // and each at most once, so no move is counted twice
+    // corners and the blank's goal move with walls, linear conflicts only there
+    fn estimate(&self, p: &Puzzle) -> u32 {
+        if p.topology == Topology::Torus {
+            return p.manhattan();
+        }
+        if p.walled {
+            return LinearConflict.estimate(p);
+        }
+        let conflicts = Conflicts::new(p);
+        let mut extra = conflicts.extra;
+        let (rows, cols) = (p.rows, p.cols);
+        let free = |idx: usize| !conflicts.involved[idx];
+        let cell_of = |tile: usize| p.grid.iter().position(|&t| t as usize == tile);
+        // the last move of an unsolved board brings the tile left of or above
+        // the blank's goal home, so one of them visits the bottom right corner first
+        let left = rows * cols - 1; // goal (rows - 1, cols - 2)
+        let above = (rows - 1) * cols; // goal (rows - 2, cols - 1)
+        let mut last_move = Vec::new();
+        let md = p.manhattan();
+        if md > 0
+            && let (Some(l), Some(a)) = (cell_of(left), cell_of(above))
+        {
+            last_move = vec![l, a];
+            if l % cols != cols - 1 && a / cols != rows - 1 && free(l) && free(a) {
+                extra += 1;
+            }
+        }
+        // the right tile reaches a corner through one of its two neighbours,
+        // if both are placed one of them steps aside; neighbours are never shared
+        if rows >= 3 && cols >= 3 {
+            let corners = [
+                (0, [1, cols]),
+                (cols - 1, [cols - 2, 2 * cols - 1]),
+                (
+                    (rows - 1) * cols,
+                    [(rows - 2) * cols, (rows - 1) * cols + 1],
+                ),
+            ];
+            let mut credited = Vec::new();
+            for (corner, neighbours) in corners {
+                if p.grid[corner] as usize == corner + 1 {
+                    continue;
+                }
+                let usable = neighbours.iter().all(|&n| {
+                    p.grid[n] as usize == n + 1
+                        && free(n)
+                        && !credited.contains(&n)
+                        && !last_move.contains(&n)
+                });
+                if usable {
+                    credited.extend(neighbours);
+                    extra += 1;
+                }
+            }
+        }
+        md + 2 * extra
+    }
+}
+
+/* Linear conflicts of a board: tiles in their goal line, out of order */
+struct Conflicts {
+    extra: u32,          // tiles that must leave their line, each costs 2 moves
+    involved: Vec<bool>, // per cell, the tile is out of order with another
+}
+
+impl Conflicts {
+    fn new(p: &Puzzle) -> Self {
+        let (rows, cols) = (p.rows, p.cols);
+        let mut conflicts = Self {
+            extra: 0,
+            involved: vec![false; p.grid.len()],
+        };
+        let goal = |idx: usize| p.grid[idx] as usize - 1;
+        for r in 0..rows {
+            let line: Vec<usize> = (0..cols)
+                .map(|c| r * cols + c)
+                .filter(|&idx| !matches!(p.grid[idx], 0 | WALL) && goal(idx) / cols == r)
+                .collect();
+            conflicts.add(&line, |idx| goal(idx) % cols);
+        }
+        for c in 0..cols {
+            let line: Vec<usize> = (0..rows)
+                .map(|r| r * cols + c)
+                .filter(|&idx| !matches!(p.grid[idx], 0 | WALL) && goal(idx) % cols == c)
+                .collect();
+            conflicts.add(&line, |idx| goal(idx) / cols);
+        }
+        conflicts
+    }
+
+    // cells of one line in order, all holding tiles that belong to it
+    fn add(&mut self, line: &[usize], target: impl Fn(usize) -> usize) {
+        let targets: Vec<usize> = line.iter().map(|&idx| target(idx)).collect();
+        for i in 0..line.len() {
+            for j in i + 1..line.len() {
+                if targets[i] > targets[j] {
+                    self.involved[line[i]] = true;
+                    self.involved[line[j]] = true;
+                }
+            }
+        }
+        // tiles outside the longest increasing run have to step out of the line
+        self.extra += (targets.len() - longest_increasing(&targets)) as u32;
+    }
+}
+
+fn longest_increasing(values: &[usize]) -> usize {
+    let mut tails: Vec<usize> = Vec::new();
+    for &v in values {
+        match tails.binary_search(&v) {
+            Ok(_) => {}
+            Err(i) if i == tails.len() => tails.push(v),
+            Err(i) => tails[i] = v,
+        }
+    }
+    tails.len()
+}
+
+/* Walking distance: vertical moves only depend on which goal row each
+ * row's tiles belong to, horizontal moves likewise for columns; both
+ * are solved exactly by BFS and added */
+#[derive(Debug, Clone)]
+pub struct WalkingDistance {
+    rows: usize,
+    cols: usize,
+    by_row: HashMap<Vec<u8>, u32>,
+    by_col: HashMap<Vec<u8>, u32>,
+}
+
+impl WalkingDistance {
+    // None when a side is longer than MAX_WALKING_SIDE
+    pub fn new(rows: usize, cols: usize) -> Option<Self> {
+        if rows == 0 || cols == 0 || rows.max(cols) > MAX_WALKING_SIDE {
+            return None;
+        }
+        Some(Self {
+            rows,
+            cols,
+            by_row: walking_table(rows, cols),
+            by_col: walking_table(cols, rows),
+        })
+    }
+
+    // counts[line * lines + goal line] of tiles, then the blank's line
+    fn key(p: &Puzzle, lines: usize, line_of: impl Fn(usize) -> usize) -> Vec<u8> {
+        let mut key = vec![0; lines * lines + 1];
+        for (idx, &tile) in p.grid.iter().enumerate() {
+            if tile == 0 {
+                key[lines * lines] = line_of(idx) as u8;
+            } else {
+                key[line_of(idx) * lines + line_of(tile as usize - 1)] += 1;
+            }
+        }
+        key
+    }
+}
+
+impl Heuristic for WalkingDistance {
+    fn name(&self) -> &'static str {
+        "walking-distance"
+    }
+
+    // 0 for boards of another size, Manhattan distance on a torus or with walls
+    fn estimate(&self, p: &Puzzle) -> u32 {
+        if (p.rows, p.cols) != (self.rows, self.cols) {
+            return 0;
+        }
+        if p.topology == Topology::Torus || p.walled {
+            return p.manhattan();
+        }
+        let cols = self.cols;
+        let rows = Self::key(p, self.rows, |idx| idx / cols);
+        let columns = Self::key(p, self.cols, |idx| idx % cols);
+        self.by_row.get(&rows).copied().unwrap_or(0)
+            + self.by_col.get(&columns).copied().unwrap_or(0)
+    }
+}
+
+// BFS over the line counts of `lines` lines of `width` cells, blank goal in the last line
+fn walking_table(lines: usize, width: usize) -> HashMap<Vec<u8>, u32> {
+    let mut goal = vec![0; lines * lines + 1];
+    for line in 0..lines {
+        goal[line * lines + line] = width as u8;
+    }
+    goal[lines * lines - 1] -= 1;
+    goal[lines * lines] = (lines - 1) as u8;
+    let mut dist = HashMap::from([(goal.clone(), 0)]);
+    let mut layer = vec![goal];
+    let mut depth = 0;
+    while !layer.is_empty() {
+        depth += 1;
+        let mut next = Vec::new();
+        for state in &layer {
+            let blank = state[lines * lines] as usize;
+            let neighbours = [blank.checked_sub(1), Some(blank + 1).filter(|&l| l < lines)];
+            // a tile of any goal line slides from the neighbour line into the blank's
+            for from in neighbours.into_iter().flatten() {
+                for target in 0..lines {
+                    if state[from * lines + target] == 0 {
+                        continue;
+                    }
+                    let mut moved = state.clone();
+                    moved[from * lines + target] -= 1;
+                    moved[blank * lines + target] += 1;
+                    moved[lines * lines] = from as u8;
+                    if !dist.contains_key(&moved) {
+                        dist.insert(moved.clone(), depth);
+                        next.push(moved);
+                    }
+                }
+            }
+        }
+        layer = next;
+    }
+    dist
+}
+
+/* Largest of several admissible estimates, itself admissible */
+pub struct MaxOf(pub Vec<Box<dyn Heuristic>>);
+
+impl MaxOf {
+    // strongest estimate the crate has for the board size
+    pub fn strongest(rows: usize, cols: usize) -> Self {
+        let mut heuristics: Vec<Box<dyn Heuristic>> = vec![Box::new(CornerLastMove)];
+        if let Some(wd) = WalkingDistance::new(rows, cols) {
+            heuristics.push(Box::new(wd));
+        }
+        MaxOf(heuristics)
+    }
+}
+
+impl Heuristic for MaxOf {
+    fn name(&self) -> &'static str {
+        "max"
+    }
+
+    fn estimate(&self, p: &Puzzle) -> u32 {
+        self.0.iter().map(|h| h.estimate(p)).max().unwrap_or(0)
+    }
+}
+
+impl Puzzle {
+    // sum over tiles of the grid distance to their goal cell
+    pub(crate) fn manhattan(&self) -> u32 {
+        (0..self.grid.len())
+            .map(|idx| self.tile_distance(idx))
+            .sum()
+    }
+
+    // grid distance between the tile at idx and its goal cell (0 for the blank and walls)
+    pub(crate) fn tile_distance(&self, idx: usize) -> u32 {
+        let tile = self.grid[idx];
+        if tile == 0 || tile == WALL {
+            return 0;
+        }
+        self.cell_distance(idx, tile as usize - 1)
+    }
+
+    // moves between two cells, the short way round on a torus
+    pub(crate) fn cell_distance(&self, from: usize, to: usize) -> u32 {
+        let axis = |a: usize, b: usize, len: usize| match self.topology {
+            Topology::Flat => a.abs_diff(b),
+            Topology::Torus => a.abs_diff(b).min(len - a.abs_diff(b)),
+        };
+        let cols = self.cols;
+        (axis(from / cols, to / cols, self.rows) + axis(from % cols, to % cols, cols)) as u32
+    }
+
+    // change in manhattan() caused by moving the blank in dir (must be legal)
+    pub(crate) fn manhattan_delta(&self, dir: Move) -> i32 {
+        let (row, col) = self.blank;
+        let Some((to_row, to_col)) = self.neighbour(self.blank, dir) else {
+            return 0;
+        };
+        let goal = self.grid[self.index(to_row, to_col)] as usize - 1;
+        let before = self.cell_distance(self.index(to_row, to_col), goal);
+        let after = self.cell_distance(self.index(row, col), goal);
+        after as i32 - before as i32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::puzzle::tables::unrank;
+
+    fn library(rows: usize, cols: usize) -> Vec<Box<dyn Heuristic>> {
+        vec![
+            Box::new(MisplacedTiles),
+            Box::new(Manhattan),
+            Box::new(LinearConflict),
+            Box::new(CornerLastMove),
+            Box::new(WalkingDistance::new(rows, cols).unwrap()),
+        ]
+    }
+
+    #[test]
+    fn test_admissible_exhaustive() {
+        for (rows, cols) in [(2, 2), (2, 3), (3, 2), (2, 4), (4, 2), (3, 3)] {
+            let table = DistanceTable::build(rows, cols).unwrap();
+            let all = MaxOf(library(rows, cols));
+            let mut tight = 0;
+            for (r, &d) in table.distances().iter().enumerate() {
+                if d == UNREACHABLE {
+                    continue;
+                }
+                let p = Puzzle::from_tiles(rows, cols, unrank(r, rows * cols)).unwrap();
+                let mut best = 0;
+                for h in &all.0 {
+                    let estimate = h.estimate(&p);
+                    assert!(
+                        estimate <= d as u32,
+                        "{} overestimates {:?}",
+                        h.name(),
+                        p.tiles()
+                    );
+                    best = best.max(estimate);
+                }
+                if r % 101 == 0 {
+                    assert_eq!(all.estimate(&p), best);
+                }
+                tight += (best == d as u32) as usize;
+            }
+            assert!(tight > 0);
+        }
+    }
+
+    #[test]
+    fn test_ordering() {
+        for seed in 0..20 {
+            let p = Puzzle::scrambled(4, 4, Scramble::Uniform, seed).unwrap();
+            let md = Manhattan.estimate(&p);
+            assert!(MisplacedTiles.estimate(&p) <= md);
+            assert!(md <= LinearConflict.estimate(&p));
+            assert!(LinearConflict.estimate(&p) <= CornerLastMove.estimate(&p));
+        }
+        let goal = Puzzle::goal(4, 4).unwrap();
+        assert!(library(4, 4).iter().all(|h| h.estimate(&goal) == 0));
+        // two tiles swapped in their goal row
+        let p = Puzzle::new(vec![vec![2, 1, 3], vec![4, 5, 6], vec![7, 8, 0]]).unwrap();
+        assert_eq!(LinearConflict.estimate(&p), 4);
+        assert!(WalkingDistance::new(5, 4).is_none());
+        assert_eq!(WalkingDistance::new(2, 2).unwrap().estimate(&p), 0);
+    }
+
+    #[test]
+    fn test_manhattan() {
+        assert_eq!(Puzzle::goal(3, 3).unwrap().manhattan(), 0);
+        let mut p = Puzzle::new(vec![vec![0, 2], vec![3, 1]]).unwrap();
+        assert_eq!(p.manhattan(), 2);
+        for dir in [Move::Right, Move::Down, Move::Left] {
+            let expected = p.manhattan() as i32 + p.manhattan_delta(dir);
+            p.perform_move(dir).unwrap();
+            assert_eq!(p.manhattan() as i32, expected);
+        }
+    }
+}
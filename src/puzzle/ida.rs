@@ -0,0 +1,265 @@
+/*
+ * ida.rs
+ * Optimal solver - iterative deepening A* straight on perform_move
+ * Manhattan distance, updated incrementally per move, as the bound
+ * or any admissible Heuristic, estimated afresh at every board
+ * practical up to 4x4, hopeless beyond
+ */
+
+use crate::puzzle::pruning::INVERSE_ONLY;
+use crate::puzzle::*;
+
+impl Puzzle {
+    // Shortest solution, leaves the board untouched
+    pub fn solve_optimal(&self) -> Result<Vec<Move>, SolveError> {
+        self.solve_optimal_with(&mut SolveOptions::default())
+    }
+
+    // Shortest solution, stopping early as the options ask
+    pub fn solve_optimal_with(&self, opts: &mut SolveOptions) -> Result<Vec<Move>, SolveError> {
+        let pruner = MovePruner::build(self.rows, self.cols, INVERSE_ONLY);
+        self.solve_optimal_pruned_with(&pruner, opts)
+    }
+
+    // Shortest solution, skipping the move strings the automaton forbids
+    // (walled boards only skip undoing the last move)
+    pub fn solve_optimal_pruned(&self, pruner: &MovePruner) -> Result<Vec<Move>, SolveError> {
+        self.solve_optimal_pruned_with(pruner, &mut SolveOptions::default())
+    }
+
+    pub fn solve_optimal_pruned_with(
+        &self,
+        pruner: &MovePruner,
+        opts: &mut SolveOptions,
+    ) -> Result<Vec<Move>, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        // a deeper automaton may forbid the only way round a wall
+        let fallback;
+        let pruner = if self.has_walls() {
+            fallback = MovePruner::build(self.rows, self.cols, INVERSE_ONLY);
+            &fallback
+        } else {
+            pruner
+        };
+        let mut ida = Ida {
+            puzzle: self.clone(),
+            path: Vec::new(),
+            nodes: 0,
+            pruner,
+        };
+        let h = ida.puzzle.manhattan();
+        let mut bound = h;
+        loop {
+            opts.checkpoint(Progress::nodes(ida.nodes, Some(bound)))?;
+            let mut halt = |nodes, bound| opts.tick(nodes, Some(bound));
+            match ida.search(0, h, bound, MovePruner::START, &mut halt)? {
+                Outcome::Found => return Ok(ida.path),
+                Outcome::Exceeded(next) => bound = next,
+            }
+        }
+    }
+
+    // Shortest solution under an admissible estimate, e.g. MaxOf::strongest
+    pub fn solve_optimal_heuristic(
+        &self,
+        heuristic: &dyn Heuristic,
+    ) -> Result<Vec<Move>, SolveError> {
+        self.solve_optimal_heuristic_with(heuristic, &mut SolveOptions::default())
+    }
+
+    pub fn solve_optimal_heuristic_with(
+        &self,
+        heuristic: &dyn Heuristic,
+        opts: &mut SolveOptions,
+    ) -> Result<Vec<Move>, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        let mut ida = HeuristicIda {
+            puzzle: self.clone(),
+            path: Vec::new(),
+            nodes: 0,
+            pruner: MovePruner::build(self.rows, self.cols, INVERSE_ONLY),
+            heuristic,
+        };
+        let mut bound = heuristic.estimate(self);
+        loop {
+            opts.checkpoint(Progress::nodes(ida.nodes, Some(bound)))?;
+            match ida.search(0, bound, MovePruner::START, opts)? {
+                Outcome::Found => return Ok(ida.path),
+                Outcome::Exceeded(next) => bound = next,
+            }
+        }
+    }
+}
+
+pub(crate) enum Outcome {
+    Found,
+    Exceeded(u32), // smallest f above the bound
+}
+
+pub(crate) struct Ida<'a> {
+    pub(crate) puzzle: Puzzle,
+    pub(crate) path: Vec<Move>,
+    pub(crate) nodes: u64,
+    pub(crate) pruner: &'a MovePruner,
+}
+
+impl Ida<'_> {
+    // depth-first search below f = bound, halt(nodes, bound) may stop it at every node
+    pub(crate) fn search<F>(
+        &mut self,
+        g: u32,
+        h: u32,
+        bound: u32,
+        state: u32, // of the pruning automaton
+        halt: &mut F,
+    ) -> Result<Outcome, SolveError>
+    where
+        F: FnMut(u64, u32) -> Result<(), SolveError>,
+    {
+        self.nodes += 1;
+        halt(self.nodes, bound)?;
+        if g + h > bound {
+            return Ok(Outcome::Exceeded(g + h));
+        }
+        // every tile home means the blank is too
+        if h == 0 {
+            return Ok(Outcome::Found);
+        }
+        let mut min = u32::MAX;
+        for dir in Move::ALL {
+            let Some(next_state) = self.pruner.step(state, dir) else {
+                continue;
+            };
+            if !self.puzzle.is_legal(dir) {
+                continue;
+            }
+            let next_h = (h as i32 + self.puzzle.manhattan_delta(dir)) as u32;
+            if self.puzzle.perform_move(dir).is_err() {
+                continue;
+            }
+            self.path.push(dir);
+            match self.search(g + 1, next_h, bound, next_state, halt)? {
+                Outcome::Found => return Ok(Outcome::Found),
+                Outcome::Exceeded(f) => min = min.min(f),
+            }
+            self.path.pop();
+            let _ = self.puzzle.perform_move(dir.inverse());
+        }
+        Ok(Outcome::Exceeded(min))
+    }
+}
+
+/* IDA* asking a Heuristic at every board */
+struct HeuristicIda<'a> {
+    puzzle: Puzzle,
+    path: Vec<Move>,
+    nodes: u64,
+    pruner: MovePruner,
+    heuristic: &'a dyn Heuristic,
+}
+
+impl HeuristicIda<'_> {
+    fn search(
+        &mut self,
+        g: u32,
+        bound: u32,
+        state: u32,
+        opts: &mut SolveOptions,
+    ) -> Result<Outcome, SolveError> {
+        self.nodes += 1;
+        opts.tick(self.nodes, Some(bound))?;
+        let h = self.heuristic.estimate(&self.puzzle);
+        if g + h > bound {
+            return Ok(Outcome::Exceeded(g + h));
+        }
+        // an estimate of 0 need not mean solved
+        if self.puzzle.is_solved() {
+            return Ok(Outcome::Found);
+        }
+        let mut min = u32::MAX;
+        for dir in Move::ALL {
+            let Some(next_state) = self.pruner.step(state, dir) else {
+                continue;
+            };
+            if self.puzzle.perform_move(dir).is_err() {
+                continue;
+            }
+            self.path.push(dir);
+            match self.search(g + 1, bound, next_state, opts)? {
+                Outcome::Found => return Ok(Outcome::Found),
+                Outcome::Exceeded(f) => min = min.min(f),
+            }
+            self.path.pop();
+            let _ = self.puzzle.perform_move(dir.inverse());
+        }
+        Ok(Outcome::Exceeded(min))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_matches_table() {
+        let table = DistanceTable::build(2, 3).unwrap();
+        for seed in 0..50 {
+            let p = Puzzle::scrambled(2, 3, Scramble::Uniform, seed).unwrap();
+            let moves = p.solve_optimal().unwrap();
+            assert_eq!(Some(moves.len() as u8), table.distance(&p));
+            let mut replay = p.clone();
+            for dir in moves {
+                replay.perform_move(dir).unwrap();
+            }
+            assert!(replay.is_solved());
+        }
+    }
+
+    #[test]
+    fn test_heuristic_matches_table() {
+        let table = DistanceTable::build(3, 3).unwrap();
+        let heuristic = MaxOf::strongest(3, 3);
+        for seed in 0..10 {
+            let p = Puzzle::scrambled(3, 3, Scramble::Uniform, seed).unwrap();
+            let moves = p.solve_optimal_heuristic(&heuristic).unwrap();
+            assert_eq!(Some(moves.len() as u8), table.distance(&p));
+        }
+        let p = Puzzle::scrambled(4, 4, Scramble::Uniform, 1).unwrap();
+        let mut opts = SolveOptions {
+            max_nodes: Some(500),
+            ..SolveOptions::default()
+        };
+        assert_eq!(
+            p.solve_optimal_heuristic_with(&MaxOf::strongest(4, 4), &mut opts),
+            Err(SolveError::NodeLimitExceeded { nodes: 500 })
+        );
+    }
+
+    #[test]
+    fn test_4x4() {
+        let p = Puzzle::new(vec![
+            vec![1, 2, 3, 4],
+            vec![5, 6, 0, 8],
+            vec![9, 10, 7, 11],
+            vec![13, 14, 15, 12],
+        ])
+        .unwrap();
+        assert_eq!(
+            p.solve_optimal().unwrap(),
+            vec![Move::Down, Move::Right, Move::Down]
+        );
+        assert!(
+            Puzzle::goal(4, 4)
+                .unwrap()
+                .solve_optimal()
+                .unwrap()
+                .is_empty()
+        );
+        let p = Puzzle::new(vec![vec![2, 1], vec![3, 0]]).unwrap();
+        assert_eq!(p.solve_optimal(), Err(SolveError::Unsolvable));
+    }
+}
@@ -0,0 +1,75 @@
+/*
+ * invariants.rs
+ * Opt-in consistency checks for the reduction solver
+ * enabled in debug builds or with the `invariants` feature
+ * everything outside the active window is solved and must never move again
+ */
+
+use crate::puzzle::*;
+
+const CHECK_INVARIANTS: bool = cfg!(any(debug_assertions, feature = "invariants"));
+
+impl Puzzle {
+    pub(crate) fn enter_phase(&mut self, phase: Phase) {
+        self.phase = phase;
+    }
+
+    // tiles above row_offset and left of col_offset are in their goal position
+    pub(crate) fn check_frozen(&self) -> Result<(), SolveError> {
+        if !CHECK_INVARIANTS {
+            return Ok(());
+        }
+        for r in 0..self.rows {
+            for c in 0..self.cols {
+                if r >= self.row_offset && c >= self.col_offset {
+                    continue;
+                }
+                let goal = ((r * self.cols + c + 1) % (self.rows * self.cols)) as u32;
+                if self.grid[self.index(r, c)] != goal {
+                    return Err(self.violation("solved tile outside the window moved"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // end of a phase: the frozen region plus the window cells placed so far
+    pub(crate) fn check_placed(&self, placed: &[(usize, usize)]) -> Result<(), SolveError> {
+        if !CHECK_INVARIANTS {
+            return Ok(());
+        }
+        self.check_frozen()?;
+        for &(r, c) in placed {
+            if self.get(r, c) != self.get_tile(r, c) {
+                return Err(self.violation("placed tile missing at the end of its phase"));
+            }
+        }
+        Ok(())
+    }
+}
+
+// the checks compile to nothing without debug assertions or the feature
+#[cfg(all(test, any(debug_assertions, feature = "invariants")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_frozen_tile_moved() {
+        let mut p = Puzzle::new(vec![vec![1, 2, 3], vec![4, 5, 6], vec![8, 7, 0]]).unwrap();
+        p.row_offset = 1;
+        p.col_offset = 1;
+        assert!(p.check_frozen().is_err());
+        p.row_offset = 0;
+        p.col_offset = 0;
+        assert!(p.check_placed(&[(0, 0), (0, 1), (0, 2)]).is_ok());
+        p.enter_phase(Phase::BottomLeftPair);
+        let err = p.check_placed(&[(2, 0)]).unwrap_err();
+        assert!(matches!(
+            err,
+            SolveError::InvariantViolated {
+                phase: Phase::BottomLeftPair,
+                ..
+            }
+        ));
+    }
+}
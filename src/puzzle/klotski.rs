@@ -0,0 +1,469 @@
+/*
+ * klotski.rs
+ * Block-sliding puzzles (Klotski, Huarong Dao)
+ * pieces are rectangles, any number of cells are empty; a move slides one
+ * piece one cell in a direction (here Move is the way the piece goes, not
+ * a blank), into cells that are empty
+ * solved once the target piece has its top-left on the goal cell
+ * pieces of one shape are interchangeable: the state key only says where
+ * each shape is, with the target piece a shape of its own
+ * solving: breadth first over state keys, so fewest one-cell moves
+ */
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+use crate::puzzle::*;
+
+// owner of a cell no piece covers
+const EMPTY: usize = usize::MAX;
+
+/* A rectangular piece, placed by its top-left cell */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Piece {
+    pub row: usize,
+    pub col: usize,
+    pub height: usize,
+    pub width: usize,
+}
+
+impl Piece {
+    // same piece one cell over, None off the top or left edge
+    fn shifted(&self, dir: Move) -> Option<Piece> {
+        let (row, col) = match dir {
+            Move::Up => (self.row.checked_sub(1)?, self.col),
+            Move::Down => (self.row + 1, self.col),
+            Move::Left => (self.row, self.col.checked_sub(1)?),
+            Move::Right => (self.row, self.col + 1),
+            Move::Forward | Move::Back => return None,
+        };
+        Some(Piece { row, col, ..*self })
+    }
+
+    fn cells(&self, cols: usize) -> impl Iterator<Item = usize> + '_ {
+        (self.row..self.row + self.height)
+            .flat_map(move |r| (self.col..self.col + self.width).map(move |c| r * cols + c))
+    }
+}
+
+/* A piece sliding one cell */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PieceMove {
+    pub piece: usize,
+    pub dir: Move,
+}
+
+/* Errors when building or moving on a block-sliding board */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KlotskiError {
+    EmptyBoard,
+    RaggedRow {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    // the cells marked with this letter do not fill a rectangle
+    NotRectangle(char),
+    // no cell is marked with the target letter
+    MissingTarget(char),
+    EmptyPiece(usize),
+    PieceOffBoard(usize),
+    Overlap(usize, usize),
+    NoSuchPiece(usize),
+    GoalOffBoard,
+    Blocked(PieceMove),
+}
+
+impl fmt::Display for KlotskiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KlotskiError::EmptyBoard => write!(f, "board has no cells"),
+            KlotskiError::RaggedRow { row, len, expected } => {
+                write!(f, "row {} has {} cells, expected {}", row, len, expected)
+            }
+            KlotskiError::NotRectangle(letter) => {
+                write!(f, "cells marked {:?} are not a rectangle", letter)
+            }
+            KlotskiError::MissingTarget(letter) => write!(f, "no piece is marked {:?}", letter),
+            KlotskiError::EmptyPiece(piece) => write!(f, "piece {} has no cells", piece),
+            KlotskiError::PieceOffBoard(piece) => write!(f, "piece {} is off the board", piece),
+            KlotskiError::Overlap(a, b) => write!(f, "pieces {} and {} overlap", a, b),
+            KlotskiError::NoSuchPiece(piece) => write!(f, "board has no piece {}", piece),
+            KlotskiError::GoalOffBoard => write!(f, "target piece cannot fit on the goal"),
+            KlotskiError::Blocked(mv) => {
+                write!(f, "piece {} cannot move {:?}", mv.piece, mv.dir)
+            }
+        }
+    }
+}
+
+impl std::error::Error for KlotskiError {}
+
+/* Board of rectangular pieces, one of them to be brought to a goal */
+#[derive(Debug, Clone)]
+pub struct Klotski {
+    rows: usize,
+    cols: usize,
+    pieces: Vec<Piece>,
+    owner: Vec<usize>, // piece covering each cell, EMPTY if none
+    shape: Vec<u8>,    // shape class of each piece, 1 for the target
+    target: usize,
+    goal: (usize, usize), // (row,col) the target's top-left has to reach
+}
+
+impl Klotski {
+    pub fn new(
+        rows: usize,
+        cols: usize,
+        pieces: Vec<Piece>,
+        target: usize,
+        goal: (usize, usize),
+    ) -> Result<Self, KlotskiError> {
+        if rows == 0 || cols == 0 {
+            return Err(KlotskiError::EmptyBoard);
+        }
+        let mut owner = vec![EMPTY; rows * cols];
+        for (i, piece) in pieces.iter().enumerate() {
+            if piece.height == 0 || piece.width == 0 {
+                return Err(KlotskiError::EmptyPiece(i));
+            }
+            if !fits(piece.row, piece.height, rows) || !fits(piece.col, piece.width, cols) {
+                return Err(KlotskiError::PieceOffBoard(i));
+            }
+            for cell in piece.cells(cols) {
+                if owner[cell] != EMPTY {
+                    return Err(KlotskiError::Overlap(owner[cell], i));
+                }
+                owner[cell] = i;
+            }
+        }
+        let Some(aim) = pieces.get(target) else {
+            return Err(KlotskiError::NoSuchPiece(target));
+        };
+        if !fits(goal.0, aim.height, rows) || !fits(goal.1, aim.width, cols) {
+            return Err(KlotskiError::GoalOffBoard);
+        }
+        // shapes numbered in (height, width) order after the target's
+        let mut shapes: Vec<(usize, usize)> = pieces.iter().map(|p| (p.height, p.width)).collect();
+        shapes.sort_unstable();
+        shapes.dedup();
+        let shape = (0..pieces.len())
+            .map(|i| {
+                if i == target {
+                    return 1;
+                }
+                let dims = (pieces[i].height, pieces[i].width);
+                shapes.iter().position(|&s| s == dims).unwrap_or(0) as u8 + 2
+            })
+            .collect();
+        Ok(Self {
+            rows,
+            cols,
+            pieces,
+            owner,
+            shape,
+            target,
+            goal,
+        })
+    }
+
+    // Board drawn as rows of letters, one letter per piece and '.' for an
+    // empty cell; pieces are numbered in reading order of their top-left
+    pub fn from_layout(
+        layout: &[&str],
+        target: char,
+        goal: (usize, usize),
+    ) -> Result<Self, KlotskiError> {
+        let rows = layout.len();
+        let cols = layout.first().map_or(0, |row| row.chars().count());
+        let mut letters: Vec<char> = Vec::new();
+        let mut bounds: Vec<(usize, usize, usize, usize, usize)> = Vec::new();
+        for (r, line) in layout.iter().enumerate() {
+            let len = line.chars().count();
+            if len != cols {
+                return Err(KlotskiError::RaggedRow {
+                    row: r,
+                    len,
+                    expected: cols,
+                });
+            }
+            for (c, letter) in line.chars().enumerate().filter(|&(_, l)| l != '.') {
+                match letters.iter().position(|&l| l == letter) {
+                    Some(i) => {
+                        let (top, left, bottom, right, count) = &mut bounds[i];
+                        *top = (*top).min(r);
+                        *left = (*left).min(c);
+                        *bottom = (*bottom).max(r);
+                        *right = (*right).max(c);
+                        *count += 1;
+                    }
+                    None => {
+                        letters.push(letter);
+                        bounds.push((r, c, r, c, 1));
+                    }
+                }
+            }
+        }
+        let mut pieces = Vec::new();
+        for (&letter, &(top, left, bottom, right, count)) in letters.iter().zip(&bounds) {
+            let (height, width) = (bottom - top + 1, right - left + 1);
+            if count != height * width {
+                return Err(KlotskiError::NotRectangle(letter));
+            }
+            pieces.push(Piece {
+                row: top,
+                col: left,
+                height,
+                width,
+            });
+        }
+        let target = letters
+            .iter()
+            .position(|&l| l == target)
+            .ok_or(KlotskiError::MissingTarget(target))?;
+        Self::new(rows, cols, pieces, target, goal)
+    }
+
+    // Huarong Dao's "Heng Dao Li Ma": Cao Cao, the 2x2, out at the bottom middle
+    pub fn classic() -> Self {
+        let layout = ["ABBC", "ABBC", "DEEF", "DGHF", "I..J"];
+        Self::from_layout(&layout, 'B', (3, 1)).expect("classic layout is valid")
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn pieces(&self) -> &[Piece] {
+        &self.pieces
+    }
+
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
+    pub fn is_legal(&self, mv: PieceMove) -> bool {
+        let Some(moved) = self.pieces.get(mv.piece).and_then(|p| p.shifted(mv.dir)) else {
+            return false;
+        };
+        moved.row + moved.height <= self.rows
+            && moved.col + moved.width <= self.cols
+            && moved
+                .cells(self.cols)
+                .all(|cell| self.owner[cell] == EMPTY || self.owner[cell] == mv.piece)
+    }
+
+    pub fn perform(&mut self, mv: PieceMove) -> Result<(), KlotskiError> {
+        if mv.piece >= self.pieces.len() {
+            return Err(KlotskiError::NoSuchPiece(mv.piece));
+        }
+        if !self.is_legal(mv) {
+            return Err(KlotskiError::Blocked(mv));
+        }
+        let piece = self.pieces[mv.piece];
+        let Some(moved) = piece.shifted(mv.dir) else {
+            return Err(KlotskiError::Blocked(mv));
+        };
+        for cell in piece.cells(self.cols) {
+            self.owner[cell] = EMPTY;
+        }
+        for cell in moved.cells(self.cols) {
+            self.owner[cell] = mv.piece;
+        }
+        self.pieces[mv.piece] = moved;
+        Ok(())
+    }
+
+    // every legal move, pieces in order
+    pub fn moves(&self) -> Vec<PieceMove> {
+        (0..self.pieces.len())
+            .flat_map(|piece| Move::ALL.map(|dir| PieceMove { piece, dir }))
+            .filter(|&mv| self.is_legal(mv))
+            .collect()
+    }
+
+    pub fn is_solved(&self) -> bool {
+        let aim = self.pieces[self.target];
+        (aim.row, aim.col) == self.goal
+    }
+
+    // Shape class whose top-left is on each cell, 0 for none: boards that
+    // differ only by swapping pieces of one shape share a key
+    pub fn key(&self) -> Vec<u8> {
+        let mut key = vec![0; self.owner.len()];
+        for (piece, shape) in self.pieces.iter().zip(&self.shape) {
+            key[piece.row * self.cols + piece.col] = *shape;
+        }
+        key
+    }
+
+    // Fewest one-cell moves, breadth first
+    pub fn solve(&self) -> Result<Vec<PieceMove>, SolveError> {
+        self.solve_with(&mut SolveOptions::default())
+    }
+
+    pub fn solve_with(&self, opts: &mut SolveOptions) -> Result<Vec<PieceMove>, SolveError> {
+        // (board, parent, move from parent)
+        let mut nodes = vec![(self.clone(), usize::MAX, None)];
+        let mut seen = HashSet::from([self.key()]);
+        let mut queue = VecDeque::from([0]);
+        while let Some(i) = queue.pop_front() {
+            opts.tick(i as u64, None)?;
+            if nodes[i].0.is_solved() {
+                let mut moves = Vec::new();
+                let mut at = i;
+                while let (_, parent, Some(mv)) = nodes[at] {
+                    moves.push(mv);
+                    at = parent;
+                }
+                moves.reverse();
+                return Ok(moves);
+            }
+            for mv in nodes[i].0.moves() {
+                let mut next = nodes[i].0.clone();
+                if next.perform(mv).is_ok() && seen.insert(next.key()) {
+                    queue.push_back(nodes.len());
+                    nodes.push((next, i, Some(mv)));
+                }
+            }
+        }
+        Err(SolveError::Unsolvable)
+    }
+}
+
+// start..start + len lies within 0..limit
+fn fits(start: usize, len: usize, limit: usize) -> bool {
+    start.checked_add(len).is_some_and(|end| end <= limit)
+}
+
+impl fmt::Display for Klotski {
+    // the layout from_layout reads, pieces lettered from 'A'
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (r, row) in self.owner.chunks(self.cols).enumerate() {
+            if r > 0 {
+                writeln!(f)?;
+            }
+            for &piece in row {
+                let letter = match piece {
+                    EMPTY => '.',
+                    _ => char::from_u32('A' as u32 + piece as u32).unwrap_or('?'),
+                };
+                write!(f, "{}", letter)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_layout_and_moves() {
+        let mut k = Klotski::from_layout(&["AAB", "..B", "C.."], 'A', (1, 1)).unwrap();
+        assert_eq!(k.pieces().len(), 3);
+        assert_eq!(k.to_string(), "AAB\n..B\nC..");
+        let down = PieceMove {
+            piece: 0,
+            dir: Move::Down,
+        };
+        // B is two cells tall, it cannot move left past A's row
+        let left = PieceMove {
+            piece: 1,
+            dir: Move::Left,
+        };
+        assert_eq!(k.perform(left), Err(KlotskiError::Blocked(left)));
+        assert_eq!(
+            k.moves(),
+            vec![
+                down,
+                PieceMove {
+                    piece: 1,
+                    dir: Move::Down
+                },
+                PieceMove {
+                    piece: 2,
+                    dir: Move::Up
+                },
+                PieceMove {
+                    piece: 2,
+                    dir: Move::Right
+                },
+            ]
+        );
+        k.perform(down).unwrap();
+        assert_eq!(k.to_string(), "..B\nAAB\nC..");
+        assert_eq!(
+            Klotski::from_layout(&["AA", "A."], 'A', (0, 0)).unwrap_err(),
+            KlotskiError::NotRectangle('A')
+        );
+        assert_eq!(
+            Klotski::from_layout(&["A.", "B."], 'C', (0, 0)).unwrap_err(),
+            KlotskiError::MissingTarget('C')
+        );
+        let piece = Piece {
+            row: 0,
+            col: 0,
+            height: 2,
+            width: 1,
+        };
+        assert_eq!(
+            Klotski::new(3, 2, vec![piece, Piece { row: 1, ..piece }], 0, (0, 0)).unwrap_err(),
+            KlotskiError::Overlap(0, 1)
+        );
+        // far corners must not wrap back onto the board
+        let unit = Piece {
+            height: 1,
+            width: 1,
+            ..piece
+        };
+        let far = Piece {
+            row: usize::MAX,
+            ..unit
+        };
+        assert_eq!(
+            Klotski::new(3, 3, vec![far], 0, (0, 0)).unwrap_err(),
+            KlotskiError::PieceOffBoard(0)
+        );
+        assert_eq!(
+            Klotski::new(3, 3, vec![unit], 0, (usize::MAX, 0)).unwrap_err(),
+            KlotskiError::GoalOffBoard
+        );
+        assert_eq!(
+            Klotski::new(3, 3, vec![unit], 0, (0, usize::MAX)).unwrap_err(),
+            KlotskiError::GoalOffBoard
+        );
+    }
+
+    #[test]
+    fn test_key_merges_equal_shapes() {
+        let a = Klotski::from_layout(&["AB.", "CCD"], 'C', (0, 0)).unwrap();
+        let b = Klotski::from_layout(&["BA.", "CCD"], 'C', (0, 0)).unwrap();
+        assert_eq!(a.key(), b.key());
+        // the target is its own shape even when another piece matches it
+        let c = Klotski::from_layout(&["A.", "B."], 'A', (1, 1)).unwrap();
+        let d = Klotski::from_layout(&["B.", "A."], 'A', (1, 1)).unwrap();
+        assert_ne!(c.key(), d.key());
+    }
+
+    #[test]
+    fn test_solves_classic() {
+        let k = Klotski::classic();
+        let moves = k.solve().unwrap();
+        let mut replay = k.clone();
+        for &mv in &moves {
+            replay.perform(mv).unwrap();
+        }
+        assert!(replay.is_solved());
+        // the known optimum counting every one-cell slide
+        assert_eq!(moves.len(), 116);
+        // a full-width piece under the 2x2 never moves
+        let stuck = Klotski::from_layout(&["AA.", "AA.", "BBB"], 'A', (1, 0)).unwrap();
+        assert_eq!(stuck.solve(), Err(SolveError::Unsolvable));
+    }
+}
@@ -1,28 +1,99 @@
+use std::fmt;
+
+mod anytime;
+mod batch;
+mod blanks;
+mod colours;
+mod control;
+mod cube;
+mod dataset;
+mod env;
+mod graph;
+mod heuristic;
+mod ida;
+mod invariants;
+mod klotski;
+mod model;
+mod parallel_ida;
+mod pruning;
+mod random;
+mod rating;
+mod rotation;
+mod search;
 mod solver;
 mod solver_2x2;
 mod solver_2x3;
 mod solver_3x2;
 mod solver_4x4;
+mod solver_line;
+mod stats;
+mod tables;
+mod torus;
+mod transposition;
 mod util;
+mod walls;
+
+pub use batch::{BatchResult, Solver, solve_batch};
+pub use blanks::{BlankMove, MultiBlank};
+pub use colours::ColouredPuzzle;
+pub use control::{CancelToken, Progress, SolveOptions};
+pub use cube::Cube;
+pub use dataset::{Dataset, DatasetConfig, DatasetError, Sample, TABLE_LABEL_CELLS, read_binary};
+pub use env::{Encoding, EnvConfig, PuzzleEnv, Shaping, Step, encode};
+pub use graph::{GraphError, GraphPuzzle};
+pub use heuristic::{
+    CornerLastMove, Heuristic, LinearConflict, MAX_WALKING_SIDE, Manhattan, MaxOf, MisplacedTiles,
+    WalkingDistance,
+};
+pub use klotski::{Klotski, KlotskiError, Piece, PieceMove};
+pub use model::{Evaluator, Layer, Mlp, ModelError};
+pub use pruning::MovePruner;
+pub use random::Scramble;
+pub use rating::{Rater, Rating, RatingConfig, Tier};
+pub use rotation::{RotationPuzzle, Spin, Turn};
+pub use search::{BeamConfig, SearchReport, WeightedConfig};
+pub use solver_4x4::ZoneOrigin;
+pub use stats::StateSpaceStats;
+pub use tables::{DistanceTable, MAX_TABLE_CELLS, TableError, UNREACHABLE};
+pub use transposition::TranspositionTable;
+pub use walls::WALL;
 
 /* Representing the moves */
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Move {
     Up,
     Down,
     Left,
     Right,
+    // between layers, only on a Cube
+    Forward,
+    Back,
+}
+
+/* How the edges of the board connect */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topology {
+    Flat,
+    // the blank leaving one edge comes back on the opposite edge
+    Torus,
 }
 
 /* Representing the Puzzle State - 4x4 board */
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Puzzle {
-    grid: Vec<u32>,        // 0 means blank piece
+    grid: Vec<u32>,        // 0 means blank piece, WALL a cell no tile may enter
     blank: (usize, usize), // (row,col) of blank piece
     rows: usize,
     cols: usize,
+    topology: Topology,
+    walled: bool, // some cell of grid is WALL
     row_offset: usize,
     col_offset: usize,
+    budget: usize,       // max moves the solver may make
+    solution: Vec<Move>, // moves made by the running solver
+    phase: Phase,        // step of the reduction the solver is in
+    banished: usize,     // pathological pairs the solver had to break up
+    zobrist: u64,        // hash of grid, kept up to date by set
 }
 
 pub enum ZonePos {
@@ -31,3 +102,141 @@ pub enum ZonePos {
     BottomRight,
     BottomLeft,
 }
+
+/* Errors when building or moving on a board */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PuzzleError {
+    EmptyBoard,
+    RaggedRow {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    // tiles must be exactly 0..rows*cols, each once
+    // (with walls: one blank and each tile once, tile t in a free cell t - 1)
+    InvalidTiles,
+    WallOutOfBounds {
+        row: usize,
+        col: usize,
+    },
+    IllegalMove(Move),
+    // a move named a blank the board does not have
+    NoSuchBlank(usize),
+    // a turn named a 2x2 zone that does not fit on the board
+    NoSuchZone {
+        row: usize,
+        col: usize,
+    },
+}
+
+impl fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PuzzleError::EmptyBoard => write!(f, "board has no cells"),
+            PuzzleError::RaggedRow { row, len, expected } => {
+                write!(f, "row {} has {} cells, expected {}", row, len, expected)
+            }
+            PuzzleError::InvalidTiles => {
+                write!(f, "tiles are not a permutation of 0..rows*cols")
+            }
+            PuzzleError::WallOutOfBounds { row, col } => {
+                write!(f, "wall at ({}, {}) is off the board", row, col)
+            }
+            PuzzleError::IllegalMove(dir) => write!(f, "blank cannot move {:?}", dir),
+            PuzzleError::NoSuchBlank(blank) => write!(f, "board has no blank {}", blank),
+            PuzzleError::NoSuchZone { row, col } => {
+                write!(f, "no 2x2 zone starts at ({}, {})", row, col)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
+/* Board state captured when the solver gives up */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub grid: Vec<u32>,
+    pub rows: usize,
+    pub cols: usize,
+    pub blank: (usize, usize), // relative to the offsets below
+    pub row_offset: usize,
+    pub col_offset: usize,
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "window at ({}, {}), blank at {:?}",
+            self.row_offset, self.col_offset, self.blank
+        )?;
+        for row in self.grid.chunks(self.cols) {
+            writeln!(f)?;
+            let cells: Vec<String> = row.iter().map(|t| format!("{:>3}", t)).collect();
+            write!(f, "{}", cells.join(""))?;
+        }
+        Ok(())
+    }
+}
+
+/* Steps of the reduction solver, reported when an invariant breaks */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    TopLeftCorner,
+    TopRowFiller(usize),
+    TopRightPair,
+    LeftColFiller(usize),
+    BottomLeftPair,
+    Block2x2,
+    Line,
+}
+
+/* Errors from the solvers */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    Unsolvable,
+    BudgetExceeded {
+        budget: usize,
+    },
+    // a bounded search ran out of states or depth without reaching the goal
+    SearchExhausted,
+    Cancelled,
+    DeadlineExceeded,
+    NodeLimitExceeded {
+        nodes: u64,
+    },
+    InvariantViolated {
+        phase: Phase,
+        reason: &'static str,
+        snapshot: Snapshot,
+    },
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::Unsolvable => write!(f, "puzzle is not solvable"),
+            SolveError::SearchExhausted => write!(f, "search gave up before reaching the goal"),
+            SolveError::Cancelled => write!(f, "solve was cancelled"),
+            SolveError::DeadlineExceeded => write!(f, "solve ran past its deadline"),
+            SolveError::NodeLimitExceeded { nodes } => {
+                write!(f, "node budget of {} exceeded", nodes)
+            }
+            SolveError::BudgetExceeded { budget } => {
+                write!(f, "move budget of {} exceeded", budget)
+            }
+            SolveError::InvariantViolated {
+                phase,
+                reason,
+                snapshot,
+            } => write!(
+                f,
+                "solver invariant violated during {:?}: {}\n{}",
+                phase, reason, snapshot
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
@@ -0,0 +1,364 @@
+/*
+ * model.rs
+ * Learned heuristics - evaluators that score a board for search
+ * small feed-forward network, plain text weights, pure CPU inference
+ */
+
+use std::fmt;
+use std::path::Path;
+
+use crate::puzzle::*;
+
+/* Anything that estimates the remaining distance of a board */
+pub trait Evaluator {
+    fn evaluate(&self, p: &Puzzle) -> f32;
+}
+
+#[derive(Debug)]
+pub enum ModelError {
+    Io(std::io::Error),
+    Parse { line: usize, reason: &'static str },
+    Shape(&'static str),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::Io(err) => write!(f, "model io failed: {}", err),
+            ModelError::Parse { line, reason } => {
+                write!(f, "model line {}: {}", line, reason)
+            }
+            ModelError::Shape(reason) => write!(f, "bad model shape: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+impl From<std::io::Error> for ModelError {
+    fn from(err: std::io::Error) -> Self {
+        ModelError::Io(err)
+    }
+}
+
+/* Dense layer, weights stored row by row (one row per output) */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub weights: Vec<f32>,
+    pub biases: Vec<f32>,
+}
+
+/* Feed-forward network: ReLU between layers, single linear output */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mlp {
+    rows: usize,
+    cols: usize,
+    encoding: Encoding,
+    layers: Vec<Layer>,
+}
+
+impl Mlp {
+    pub fn new(
+        rows: usize,
+        cols: usize,
+        encoding: Encoding,
+        layers: Vec<Layer>,
+    ) -> Result<Self, ModelError> {
+        let mut width = input_width(rows, cols, encoding)?;
+        for layer in &layers {
+            // weights are read in rows of `inputs`, an empty row cannot be split
+            if layer.inputs == 0 || layer.outputs == 0 {
+                return Err(ModelError::Shape("layer has no inputs or no outputs"));
+            }
+            if layer.inputs != width {
+                return Err(ModelError::Shape(
+                    "layer inputs do not match previous layer",
+                ));
+            }
+            if layer.weights.len() != layer.inputs * layer.outputs
+                || layer.biases.len() != layer.outputs
+            {
+                return Err(ModelError::Shape("layer has the wrong number of weights"));
+            }
+            width = layer.outputs;
+        }
+        if layers.is_empty() || width != 1 {
+            return Err(ModelError::Shape("network must end in a single output"));
+        }
+        Ok(Self {
+            rows,
+            cols,
+            encoding,
+            layers,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /* Text format, whitespace separated, `#` starts a comment
+     *   mlp <rows> <cols> <flat|onehot>
+     *   layer <inputs> <outputs>
+     *   <outputs * inputs weights, row per output> <outputs biases>
+     *   layer ...
+     */
+    pub fn parse(text: &str) -> Result<Self, ModelError> {
+        let mut tokens = Tokens::new(text);
+        tokens.keyword("mlp")?;
+        let rows = tokens.number("missing rows")?;
+        let cols = tokens.number("missing cols")?;
+        let encoding = match tokens.next("missing encoding")? {
+            "flat" => Encoding::Flat,
+            "onehot" => Encoding::OneHot,
+            _ => return Err(tokens.error("unknown encoding")),
+        };
+        let mut width = input_width(rows, cols, encoding)?;
+        let mut layers = Vec::new();
+        while !tokens.is_empty() {
+            tokens.keyword("layer")?;
+            let inputs: usize = tokens.number("missing layer inputs")?;
+            let outputs: usize = tokens.number("missing layer outputs")?;
+            // check the header before trusting it with an allocation
+            if inputs != width {
+                return Err(ModelError::Shape(
+                    "layer inputs do not match previous layer",
+                ));
+            }
+            let count = match inputs.checked_mul(outputs) {
+                Some(count) if count > 0 => count,
+                _ => return Err(ModelError::Shape("layer has no inputs or no outputs")),
+            };
+            width = outputs;
+            let mut weights = Vec::with_capacity(count);
+            for _ in 0..count {
+                weights.push(tokens.number("missing weight")?);
+            }
+            let mut biases = Vec::with_capacity(outputs);
+            for _ in 0..outputs {
+                biases.push(tokens.number("missing bias")?);
+            }
+            layers.push(Layer {
+                inputs,
+                outputs,
+                weights,
+                biases,
+            });
+        }
+        Self::new(rows, cols, encoding, layers)
+    }
+
+    // inverse of parse
+    pub fn to_text(&self) -> String {
+        let encoding = match self.encoding {
+            Encoding::Flat => "flat",
+            Encoding::OneHot => "onehot",
+        };
+        let mut out = format!("mlp {} {} {}\n", self.rows, self.cols, encoding);
+        for layer in &self.layers {
+            out += &format!("layer {} {}\n", layer.inputs, layer.outputs);
+            for row in layer.weights.chunks(layer.inputs) {
+                let row: Vec<String> = row.iter().map(|w| w.to_string()).collect();
+                out += &row.join(" ");
+                out += "\n";
+            }
+            let biases: Vec<String> = layer.biases.iter().map(|b| b.to_string()).collect();
+            out += &biases.join(" ");
+            out += "\n";
+        }
+        out
+    }
+}
+
+// inputs of the first layer
+fn input_width(rows: usize, cols: usize, encoding: Encoding) -> Result<usize, ModelError> {
+    let cells = rows.checked_mul(cols);
+    let width = match encoding {
+        Encoding::Flat => cells,
+        Encoding::OneHot => cells.and_then(|cells| cells.checked_mul(cells)),
+    };
+    width.ok_or(ModelError::Shape("board too large"))
+}
+
+/* Whitespace separated tokens tagged with their line, comments dropped */
+struct Tokens<'a> {
+    tokens: std::iter::Peekable<std::vec::IntoIter<(usize, &'a str)>>,
+    line: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(text: &'a str) -> Self {
+        let tokens: Vec<(usize, &str)> = text
+            .lines()
+            .enumerate()
+            .flat_map(|(i, line)| {
+                let line = line.split('#').next().unwrap_or("");
+                line.split_whitespace().map(move |tok| (i + 1, tok))
+            })
+            .collect();
+        Self {
+            tokens: tokens.into_iter().peekable(),
+            line: 1,
+        }
+    }
+
+    fn is_empty(&mut self) -> bool {
+        self.tokens.peek().is_none()
+    }
+
+    fn error(&self, reason: &'static str) -> ModelError {
+        ModelError::Parse {
+            line: self.line,
+            reason,
+        }
+    }
+
+    fn next(&mut self, reason: &'static str) -> Result<&'a str, ModelError> {
+        let (line, tok) = self.tokens.next().ok_or(self.error(reason))?;
+        self.line = line;
+        Ok(tok)
+    }
+
+    fn keyword(&mut self, word: &'static str) -> Result<(), ModelError> {
+        match self.next("missing keyword")? {
+            tok if tok == word => Ok(()),
+            _ => Err(self.error("unexpected keyword")),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, reason: &'static str) -> Result<T, ModelError> {
+        self.next(reason)?
+            .parse()
+            .map_err(|_| self.error("expected a number"))
+    }
+}
+
+impl Evaluator for Mlp {
+    fn evaluate(&self, p: &Puzzle) -> f32 {
+        if (p.rows, p.cols) != (self.rows, self.cols) {
+            return f32::INFINITY;
+        }
+        let mut values = Vec::new();
+        for (i, layer) in self.layers.iter().enumerate() {
+            let rows = layer.weights.chunks(layer.inputs).zip(&layer.biases);
+            let sums: Vec<f32> = match (i, self.encoding) {
+                // one-hot input has a single active entry per cell, skip the zeros
+                (0, Encoding::OneHot) => {
+                    let cells = p.grid.len();
+                    rows.map(|(row, bias)| {
+                        let active = p.grid.iter().enumerate().filter(|&(_, &t)| t != WALL);
+                        active
+                            .map(|(idx, &t)| row[idx * cells + t as usize])
+                            .sum::<f32>()
+                            + bias
+                    })
+                    .collect()
+                }
+                (0, Encoding::Flat) => {
+                    let input = encode(p, Encoding::Flat);
+                    rows.map(|(row, bias)| dot(row, &input) + bias).collect()
+                }
+                _ => rows.map(|(row, bias)| dot(row, &values) + bias).collect(),
+            };
+            let last = i + 1 == self.layers.len();
+            values = if last {
+                sums
+            } else {
+                sums.into_iter().map(|v| v.max(0.0)).collect()
+            };
+        }
+        values[0]
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    // one-hot linear model whose weights are exactly the Manhattan distance
+    pub(crate) fn manhattan_model(rows: usize, cols: usize) -> Mlp {
+        let cells = rows * cols;
+        let mut weights = vec![0.0; cells * cells];
+        for cell in 0..cells {
+            for tile in 1..cells {
+                let goal = tile - 1;
+                weights[cell * cells + tile] = ((cell / cols).abs_diff(goal / cols)
+                    + (cell % cols).abs_diff(goal % cols))
+                    as f32;
+            }
+        }
+        let layer = Layer {
+            inputs: cells * cells,
+            outputs: 1,
+            weights,
+            biases: vec![0.0],
+        };
+        Mlp::new(rows, cols, Encoding::OneHot, vec![layer]).unwrap()
+    }
+
+    #[test]
+    fn test_linear_model_is_manhattan() {
+        let model = manhattan_model(4, 4);
+        for seed in 0..20 {
+            let p = Puzzle::scrambled(4, 4, Scramble::Uniform, seed).unwrap();
+            assert_eq!(model.evaluate(&p), p.manhattan() as f32);
+        }
+    }
+
+    #[test]
+    fn test_text_roundtrip() {
+        let text = "# tiny net\n\
+                    mlp 2 2 flat\n\
+                    layer 4 2\n\
+                    1 0 0 0\n\
+                    0 -1 0 0\n\
+                    0.5 0\n\
+                    layer 2 1\n\
+                    2 3 1 # output\n";
+        let model = Mlp::parse(text).unwrap();
+        let p = Puzzle::new(vec![vec![3, 1], vec![2, 0]]).unwrap();
+        // relu(3 + 0.5) * 2 + relu(-1) * 3 + 1
+        assert_eq!(model.evaluate(&p), 8.0);
+        assert_eq!(Mlp::parse(&model.to_text()).unwrap(), model);
+    }
+
+    #[test]
+    fn test_bad_files() {
+        assert!(matches!(
+            Mlp::parse("mlp 2 2 flat\nlayer 4 1\n1 2 3"),
+            Err(ModelError::Parse { line: 3, .. })
+        ));
+        assert!(matches!(
+            Mlp::parse("mlp 2 2 flat\nlayer 3 1\n1 2 3 0"),
+            Err(ModelError::Shape(_))
+        ));
+        // headers are checked before any weights are read
+        assert!(matches!(
+            Mlp::parse("mlp 2 2 flat\nlayer 4000000000000 4000000000000\n"),
+            Err(ModelError::Shape(_))
+        ));
+        assert!(matches!(
+            Mlp::parse("mlp 4000000000000 4000000000000 onehot\nlayer 1 1\n"),
+            Err(ModelError::Shape(_))
+        ));
+        // zero-width layers
+        assert!(matches!(
+            Mlp::parse("mlp 2 2 flat\nlayer 4 0\nlayer 0 1\n0"),
+            Err(ModelError::Shape(_))
+        ));
+        assert!(matches!(
+            Mlp::parse("mlp 0 2 flat\nlayer 0 1\n0"),
+            Err(ModelError::Shape(_))
+        ));
+        assert!(matches!(
+            Mlp::parse("net 2 2 flat"),
+            Err(ModelError::Parse { line: 1, .. })
+        ));
+    }
+}
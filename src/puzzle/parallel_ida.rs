@@ -0,0 +1,289 @@
+/*
+ * parallel_ida.rs
+ * Parallel optimal solver - IDA* with the tree split at shallow depth
+ * every iteration
+ *   - expands the root breadth first until there are enough subtrees
+ *   - workers pull subtrees from a shared queue until it is empty,
+ *     so a thread that finishes early takes over the remaining work
+ *   - the next bound is the minimum over all workers, kept in an atomic
+ *   - the first worker to reach the goal halts the others
+ * same optimal length as solve_optimal
+ */
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Instant;
+
+use crate::puzzle::control::CHECK_EVERY;
+use crate::puzzle::ida::{Ida, Outcome};
+use crate::puzzle::pruning::INVERSE_ONLY;
+use crate::puzzle::*;
+
+// subtrees per worker, more means better balance but more duplicated setup
+const TASKS_PER_THREAD: usize = 64;
+const MAX_SPLIT_DEPTH: usize = 16;
+
+/* Subtree of one iteration: board, path from the root, its Manhattan distance */
+struct Task {
+    puzzle: Puzzle,
+    path: Vec<Move>,
+    h: u32,
+    state: u32, // of the pruning automaton
+}
+
+enum Split {
+    Found(Vec<Move>),
+    Tasks(Vec<Task>, u32), // subtrees and the smallest f pruned while splitting
+}
+
+/* State shared by the workers of one iteration */
+struct Shared {
+    next_task: AtomicUsize,
+    next_bound: AtomicU32,
+    nodes: AtomicU64,
+    halted: AtomicBool,
+    found: Mutex<Option<Vec<Move>>>,
+    error: Mutex<Option<SolveError>>,
+}
+
+impl Puzzle {
+    // Shortest solution on `threads` workers (0 picks the number of cores)
+    pub fn solve_optimal_parallel(&self, threads: usize) -> Result<Vec<Move>, SolveError> {
+        self.solve_optimal_parallel_with(threads, &mut SolveOptions::default())
+    }
+
+    // progress is reported once per iteration, from the calling thread
+    pub fn solve_optimal_parallel_with(
+        &self,
+        threads: usize,
+        opts: &mut SolveOptions,
+    ) -> Result<Vec<Move>, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let pruner = MovePruner::build(self.rows, self.cols, INVERSE_ONLY);
+        let mut nodes = 0;
+        let mut bound = self.manhattan();
+        loop {
+            opts.checkpoint(Progress::nodes(nodes, Some(bound)))?;
+            let (tasks, pruned) =
+                match self.split(&pruner, bound, threads * TASKS_PER_THREAD, &mut nodes) {
+                    Split::Found(path) => return Ok(path),
+                    Split::Tasks(tasks, pruned) => (tasks, pruned),
+                };
+            let shared = Shared {
+                next_task: AtomicUsize::new(0),
+                next_bound: AtomicU32::new(pruned),
+                nodes: AtomicU64::new(nodes),
+                halted: AtomicBool::new(false),
+                found: Mutex::new(None),
+                error: Mutex::new(None),
+            };
+            let limits = (opts.cancel.clone(), opts.deadline, opts.max_nodes);
+            thread::scope(|scope| {
+                for _ in 0..threads.min(tasks.len()) {
+                    scope.spawn(|| work(&tasks, &pruner, bound, &shared, &limits));
+                }
+            });
+            nodes = shared.nodes.load(Ordering::Relaxed);
+            if let Some(path) = shared.found.into_inner().unwrap_or_else(|e| e.into_inner()) {
+                return Ok(path);
+            }
+            if let Some(err) = shared.error.into_inner().unwrap_or_else(|e| e.into_inner()) {
+                return Err(err);
+            }
+            bound = shared.next_bound.load(Ordering::Relaxed);
+            if bound == u32::MAX {
+                return Err(SolveError::SearchExhausted);
+            }
+        }
+    }
+
+    // expand the tree below `bound` breadth first until there are `want` subtrees
+    fn split(&self, pruner: &MovePruner, bound: u32, want: usize, nodes: &mut u64) -> Split {
+        let h = self.manhattan();
+        if h == 0 {
+            return Split::Found(Vec::new());
+        }
+        let mut pruned = u32::MAX;
+        let mut layer = vec![Task {
+            puzzle: self.clone(),
+            path: Vec::new(),
+            h,
+            state: MovePruner::START,
+        }];
+        for _ in 0..MAX_SPLIT_DEPTH {
+            if layer.len() >= want {
+                break;
+            }
+            let mut next = Vec::new();
+            for task in &layer {
+                *nodes += 1;
+                for dir in Move::ALL {
+                    let Some(state) = pruner.step(task.state, dir) else {
+                        continue;
+                    };
+                    if !task.puzzle.is_legal(dir) {
+                        continue;
+                    }
+                    let h = (task.h as i32 + task.puzzle.manhattan_delta(dir)) as u32;
+                    let g = task.path.len() as u32 + 1;
+                    let mut path = task.path.clone();
+                    path.push(dir);
+                    if g + h > bound {
+                        pruned = pruned.min(g + h);
+                        continue;
+                    }
+                    if h == 0 {
+                        return Split::Found(path);
+                    }
+                    let mut puzzle = task.puzzle.clone();
+                    let _ = puzzle.perform_move(dir);
+                    next.push(Task {
+                        puzzle,
+                        path,
+                        h,
+                        state,
+                    });
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            layer = next;
+        }
+        Split::Tasks(layer, pruned)
+    }
+}
+
+// worker loop: search subtrees until the queue is empty or someone halts
+fn work(
+    tasks: &[Task],
+    pruner: &MovePruner,
+    bound: u32,
+    shared: &Shared,
+    (cancel, deadline, max_nodes): &(Option<CancelToken>, Option<Instant>, Option<u64>),
+) {
+    let mut pending = 0;
+    let mut halt = |_, _| {
+        pending += 1;
+        if pending < CHECK_EVERY {
+            return Ok(());
+        }
+        let nodes = shared.nodes.fetch_add(pending, Ordering::Relaxed) + pending;
+        pending = 0;
+        let stop = if shared.halted.load(Ordering::Relaxed) {
+            // another worker finished or failed, the reason is already recorded
+            return Err(SolveError::Cancelled);
+        } else if cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            SolveError::Cancelled
+        } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            SolveError::DeadlineExceeded
+        } else if let Some(max) = max_nodes.filter(|&max| nodes > max) {
+            SolveError::NodeLimitExceeded { nodes: max }
+        } else {
+            return Ok(());
+        };
+        record(&shared.error, stop);
+        shared.halted.store(true, Ordering::Relaxed);
+        Err(SolveError::Cancelled)
+    };
+    loop {
+        let i = shared.next_task.fetch_add(1, Ordering::Relaxed);
+        let Some(task) = tasks.get(i) else {
+            break;
+        };
+        let mut ida = Ida {
+            puzzle: task.puzzle.clone(),
+            path: task.path.clone(),
+            nodes: 0,
+            pruner,
+        };
+        let g = task.path.len() as u32;
+        match ida.search(g, task.h, bound, task.state, &mut halt) {
+            Ok(Outcome::Found) => {
+                record(&shared.found, ida.path);
+                shared.halted.store(true, Ordering::Relaxed);
+                break;
+            }
+            Ok(Outcome::Exceeded(f)) => {
+                shared.next_bound.fetch_min(f, Ordering::Relaxed);
+            }
+            Err(_) => break,
+        }
+    }
+    shared.nodes.fetch_add(pending, Ordering::Relaxed);
+}
+
+// keep the first value written
+fn record<T>(slot: &Mutex<Option<T>>, value: T) {
+    let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+    slot.get_or_insert(value);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_matches_sequential() {
+        for seed in 0..10 {
+            let p = Puzzle::scrambled(3, 3, Scramble::Uniform, seed).unwrap();
+            let sequential = p.solve_optimal().unwrap();
+            for threads in [1, 4] {
+                let moves = p.solve_optimal_parallel(threads).unwrap();
+                assert_eq!(moves.len(), sequential.len());
+                let mut replay = p.clone();
+                for dir in moves {
+                    replay.perform_move(dir).unwrap();
+                }
+                assert!(replay.is_solved());
+            }
+        }
+        let p = Puzzle::scrambled(4, 4, Scramble::Walk(60), 3).unwrap();
+        assert_eq!(
+            p.solve_optimal_parallel(0).unwrap().len(),
+            p.solve_optimal().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        let goal = Puzzle::goal(4, 4).unwrap();
+        assert!(goal.solve_optimal_parallel(4).unwrap().is_empty());
+        // solved while splitting
+        let mut p = goal.clone();
+        p.perform_move(Move::Up).unwrap();
+        assert_eq!(p.solve_optimal_parallel(4), Ok(vec![Move::Down]));
+        let p = Puzzle::new(vec![vec![2, 1], vec![3, 0]]).unwrap();
+        assert_eq!(p.solve_optimal_parallel(4), Err(SolveError::Unsolvable));
+    }
+
+    #[test]
+    fn test_stops() {
+        let p = Puzzle::scrambled(5, 5, Scramble::Uniform, 1).unwrap();
+        let mut opts = SolveOptions {
+            max_nodes: Some(100_000),
+            ..SolveOptions::default()
+        };
+        assert_eq!(
+            p.solve_optimal_parallel_with(4, &mut opts),
+            Err(SolveError::NodeLimitExceeded { nodes: 100_000 })
+        );
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let mut opts = SolveOptions {
+            cancel: Some(cancel),
+            ..SolveOptions::default()
+        };
+        assert_eq!(
+            p.solve_optimal_parallel_with(4, &mut opts),
+            Err(SolveError::Cancelled)
+        );
+    }
+}
@@ -0,0 +1,316 @@
+/*
+ * pruning.rs
+ * Move-pruning automaton - skip move strings that only repeat work
+ *   - breadth first over move strings, played on an unbounded board
+ *   - a string is forbidden when an earlier string (shorter, or as long and
+ *     earlier in Move::ALL order) has the same effect and keeps the blank
+ *     inside the cells the string itself visits, so it is legal wherever
+ *     the forbidden one is
+ *   - forbidden strings are compiled into an Aho-Corasick automaton,
+ *     searches carry its state and drop moves that complete a forbidden string
+ * depth 2 forbids exactly the moves that undo the previous one
+ * deeper automata assume a board without walls, a wall may sit in the box
+ * the forbidden string's substitute passes through, so the solvers drop back
+ * to depth 2 on walled boards
+ */
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::puzzle::*;
+
+// depth the optimal solvers use when not handed an automaton
+pub(crate) const INVERSE_ONLY: usize = 2;
+
+type Cell = (i32, i32);
+
+/* Cells visited by the blank, relative to where it started */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Extent {
+    top: i32,
+    bottom: i32,
+    left: i32,
+    right: i32,
+}
+
+impl Extent {
+    fn grow(&mut self, (r, c): Cell) {
+        self.top = self.top.min(r);
+        self.bottom = self.bottom.max(r);
+        self.left = self.left.min(c);
+        self.right = self.right.max(c);
+    }
+
+    fn within(&self, other: &Extent) -> bool {
+        other.top <= self.top
+            && self.bottom <= other.bottom
+            && other.left <= self.left
+            && self.right <= other.right
+    }
+
+    fn fits(&self, rows: usize, cols: usize) -> bool {
+        ((self.bottom - self.top) as usize) < rows && ((self.right - self.left) as usize) < cols
+    }
+}
+
+/* A move string played on an unbounded board, every tile named by its start cell */
+#[derive(Debug, Clone)]
+struct Effect {
+    blank: Cell,
+    moved: Vec<(Cell, Cell)>, // (cell, start cell of the tile on it), sorted, displaced tiles only
+    extent: Extent,
+}
+
+impl Effect {
+    fn new() -> Self {
+        Self {
+            blank: (0, 0),
+            moved: Vec::new(),
+            extent: Extent {
+                top: 0,
+                bottom: 0,
+                left: 0,
+                right: 0,
+            },
+        }
+    }
+
+    // None for a move between layers, flat boards have none
+    fn play(&self, dir: Move) -> Option<Self> {
+        let (r, c) = self.blank;
+        let next = match dir {
+            Move::Up => (r - 1, c),
+            Move::Down => (r + 1, c),
+            Move::Left => (r, c - 1),
+            Move::Right => (r, c + 1),
+            Move::Forward | Move::Back => return None,
+        };
+        let mut moved = self.moved.clone();
+        // the tile next to the blank slides into the blank's cell
+        let origin = match moved.iter().position(|&(cell, _)| cell == next) {
+            Some(i) => moved.remove(i).1,
+            None => next,
+        };
+        if origin != self.blank {
+            moved.push((self.blank, origin));
+            moved.sort_unstable();
+        }
+        let mut extent = self.extent;
+        extent.grow(next);
+        Some(Self {
+            blank: next,
+            moved,
+            extent,
+        })
+    }
+
+    fn key(&self) -> (Cell, Vec<(Cell, Cell)>) {
+        (self.blank, self.moved.clone())
+    }
+}
+
+/* Automaton over moves, state 0 is the start */
+#[derive(Debug, Clone)]
+pub struct MovePruner {
+    next: Vec<[u32; 4]>, // transitions, indexed like Move::ALL
+    dead: Vec<bool>,     // reaching this state completes a forbidden string
+    forbidden: Vec<Vec<Move>>,
+}
+
+impl MovePruner {
+    pub const START: u32 = 0;
+
+    // Forbidden strings up to `depth` moves for boards of rows x cols
+    pub fn build(rows: usize, cols: usize, depth: usize) -> Self {
+        let forbidden = forbidden_strings(rows, cols, depth);
+        let mut pruner = Self {
+            next: vec![[0; 4]],
+            dead: vec![false],
+            forbidden: Vec::new(),
+        };
+        // trie of the forbidden strings, 0 marks a missing edge until the links are in
+        for string in &forbidden {
+            let mut state = 0;
+            for &dir in string {
+                // the strings come from Move::ALL, every move has a slot
+                let Some(slot) = code(dir) else {
+                    continue;
+                };
+                if pruner.next[state][slot] == 0 {
+                    pruner.next.push([0; 4]);
+                    pruner.dead.push(false);
+                    pruner.next[state][slot] = (pruner.next.len() - 1) as u32;
+                }
+                state = pruner.next[state][slot] as usize;
+            }
+            pruner.dead[state] = true;
+        }
+        // failure links breadth first, missing edges follow the link of their state
+        let mut link = vec![0; pruner.next.len()];
+        let mut queue: VecDeque<usize> = pruner.next[0]
+            .iter()
+            .filter(|&&child| child != 0)
+            .map(|&child| child as usize)
+            .collect();
+        while let Some(state) = queue.pop_front() {
+            pruner.dead[state] |= pruner.dead[link[state]];
+            for slot in 0..4 {
+                let child = pruner.next[state][slot] as usize;
+                if child == 0 {
+                    pruner.next[state][slot] = pruner.next[link[state]][slot];
+                } else {
+                    link[child] = pruner.next[link[state]][slot] as usize;
+                    queue.push_back(child);
+                }
+            }
+        }
+        pruner.forbidden = forbidden;
+        pruner
+    }
+
+    // State after playing dir, None when that completes a forbidden string
+    // (or dir moves between layers, which no flat board allows)
+    pub fn step(&self, state: u32, dir: Move) -> Option<u32> {
+        let next = self.next[state as usize][code(dir)?];
+        (!self.dead[next as usize]).then_some(next)
+    }
+
+    pub fn states(&self) -> usize {
+        self.next.len()
+    }
+
+    // shortest forbidden strings, breadth first order
+    pub fn forbidden(&self) -> &[Vec<Move>] {
+        &self.forbidden
+    }
+}
+
+// index into Move::ALL, None for a move between layers
+fn code(dir: Move) -> Option<usize> {
+    match dir {
+        Move::Up => Some(0),
+        Move::Down => Some(1),
+        Move::Left => Some(2),
+        Move::Right => Some(3),
+        Move::Forward | Move::Back => None,
+    }
+}
+
+// breadth first duplicate detection, only strings without a forbidden part
+fn forbidden_strings(rows: usize, cols: usize, depth: usize) -> Vec<Vec<Move>> {
+    let mut forbidden = Vec::new();
+    let mut banned = HashSet::new();
+    let start = Effect::new();
+    let mut seen: HashMap<_, Vec<Extent>> = HashMap::from([(start.key(), vec![start.extent])]);
+    let mut layer = vec![(Vec::new(), start)];
+    for _ in 0..depth {
+        let mut next = Vec::new();
+        for (string, effect) in &layer {
+            for dir in Move::ALL {
+                let mut string = string.clone();
+                string.push(dir);
+                // the prefix is clean, only suffixes can be forbidden already
+                if (1..string.len()).any(|i| banned.contains(&string[i..])) {
+                    continue;
+                }
+                let Some(effect) = effect.play(dir) else {
+                    continue;
+                };
+                if !effect.extent.fits(rows, cols) {
+                    continue;
+                }
+                let earlier = seen.entry(effect.key()).or_default();
+                if earlier.iter().any(|extent| extent.within(&effect.extent)) {
+                    banned.insert(string.clone());
+                    forbidden.push(string);
+                    continue;
+                }
+                earlier.push(effect.extent);
+                next.push((string, effect));
+            }
+        }
+        layer = next;
+    }
+    forbidden
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // follow the automaton, None if the string is pruned anywhere
+    fn run(pruner: &MovePruner, moves: &[Move]) -> Option<u32> {
+        moves
+            .iter()
+            .try_fold(MovePruner::START, |state, &dir| pruner.step(state, dir))
+    }
+
+    #[test]
+    fn test_inverse_only() {
+        let pruner = MovePruner::build(4, 4, INVERSE_ONLY);
+        assert_eq!(pruner.forbidden().len(), 4);
+        assert_eq!(run(&pruner, &[Move::Up, Move::Down]), None);
+        assert!(run(&pruner, &[Move::Up, Move::Left, Move::Down, Move::Right]).is_some());
+        // flat boards never move between layers
+        assert_eq!(pruner.step(MovePruner::START, Move::Forward), None);
+        assert_eq!(pruner.step(MovePruner::START, Move::Back), None);
+    }
+
+    #[test]
+    fn test_2x2_loops() {
+        let pruner = MovePruner::build(4, 4, 8);
+        // twice around a square one way is once around the other way
+        let clockwise = [Move::Right, Move::Down, Move::Left, Move::Up];
+        assert_eq!(run(&pruner, &clockwise.repeat(2)), None);
+        assert!(run(&pruner, &clockwise).is_some());
+        // a 1-wide board cannot loop, nothing beyond undoing moves is forbidden
+        let line = MovePruner::build(1, 6, 8);
+        assert_eq!(line.forbidden().len(), 2);
+    }
+
+    #[test]
+    fn test_pruned_ida_is_optimal() {
+        let table = DistanceTable::build(3, 3).unwrap();
+        let pruner = MovePruner::build(3, 3, 10);
+        for seed in 0..20 {
+            let p = Puzzle::scrambled(3, 3, Scramble::Uniform, seed).unwrap();
+            let moves = p.solve_optimal_pruned(&pruner).unwrap();
+            assert_eq!(Some(moves.len() as u8), table.distance(&p));
+            let mut replay = p.clone();
+            for dir in moves {
+                replay.perform_move(dir).unwrap();
+            }
+            assert!(replay.is_solved());
+        }
+    }
+
+    #[test]
+    fn test_pruned_ida_on_walls() {
+        let goal = Puzzle::with_walls(3, 4, &[(1, 1)]).unwrap();
+        let pruner = MovePruner::build(3, 4, 10);
+        for seed in [9, 77] {
+            let p = goal.walked(40, seed);
+            let moves = p.solve_optimal_pruned(&pruner).unwrap();
+            assert_eq!(moves.len(), p.solve_optimal().unwrap().len());
+        }
+    }
+
+    #[test]
+    fn test_fewer_nodes() {
+        let p = Puzzle::scrambled(4, 4, Scramble::Walk(60), 4).unwrap();
+        let mut counts = Vec::new();
+        for depth in [INVERSE_ONLY, 10] {
+            let pruner = MovePruner::build(4, 4, depth);
+            let mut last = 0;
+            let mut record = |progress: &Progress| last = progress.nodes;
+            let mut opts = SolveOptions {
+                progress: Some(&mut record),
+                ..SolveOptions::default()
+            };
+            let moves = p.solve_optimal_pruned_with(&pruner, &mut opts).unwrap();
+            drop(opts);
+            counts.push((moves.len(), last));
+        }
+        assert_eq!(counts[0].0, counts[1].0);
+        assert!(counts[1].1 < counts[0].1);
+    }
+}
@@ -0,0 +1,156 @@
+/*
+ * random.rs
+ * Seeded randomness, reproducible across runs and platforms
+ * scrambled boards for environments and datasets
+ */
+
+use crate::puzzle::*;
+
+/* SplitMix64 - small, fast and good enough for shuffling */
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // uniform in 0..n (n > 0)
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+/* How a scrambled board is produced */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scramble {
+    // uniformly random among all solvable states
+    Uniform,
+    // random walk of this many moves from the goal, never undoing the last move
+    Walk(usize),
+}
+
+impl Puzzle {
+    // Solved board of the given size
+    pub fn goal(rows: usize, cols: usize) -> Result<Self, PuzzleError> {
+        Self::from_tiles(rows, cols, (1..(rows * cols) as u32).chain([0]).collect())
+    }
+
+    // Solvable board scrambled from a seed
+    pub fn scrambled(
+        rows: usize,
+        cols: usize,
+        scramble: Scramble,
+        seed: u64,
+    ) -> Result<Self, PuzzleError> {
+        Self::scrambled_with(rows, cols, scramble, &mut Rng::new(seed))
+    }
+
+    pub(crate) fn scrambled_with(
+        rows: usize,
+        cols: usize,
+        scramble: Scramble,
+        rng: &mut Rng,
+    ) -> Result<Self, PuzzleError> {
+        let mut p = Self::goal(rows, cols)?;
+        match scramble {
+            Scramble::Uniform if rows == 1 || cols == 1 => {
+                // only the blank position is free on a line
+                let blank = rng.below(rows * cols);
+                let mut tiles: Vec<u32> = (1..(rows * cols) as u32).collect();
+                tiles.insert(blank, 0);
+                p = Self::from_tiles(rows, cols, tiles)?;
+            }
+            Scramble::Uniform => {
+                let mut tiles = p.grid.clone();
+                for i in (1..tiles.len()).rev() {
+                    tiles.swap(i, rng.below(i + 1));
+                }
+                p = Self::from_tiles(rows, cols, tiles)?;
+                if !p.is_solvable() {
+                    // swapping two tiles flips the parity
+                    let mut pair = (0..p.grid.len()).filter(|&i| p.grid[i] != 0);
+                    if let (Some(a), Some(b)) = (pair.next(), pair.next()) {
+                        let (tile_a, tile_b) = (p.grid[a], p.grid[b]);
+                        p.set(a / cols, a % cols, tile_b);
+                        p.set(b / cols, b % cols, tile_a);
+                    }
+                }
+            }
+            Scramble::Walk(len) => p.walk(len, rng),
+        }
+        Ok(p)
+    }
+
+    // Board a seeded random walk away from this one, for any shape of board
+    pub fn walked(&self, len: usize, seed: u64) -> Self {
+        let mut p = self.clone();
+        p.walk(len, &mut Rng::new(seed));
+        p
+    }
+
+    // random walk of len moves, never undoing the last move
+    fn walk(&mut self, len: usize, rng: &mut Rng) {
+        let mut last: Option<Move> = None;
+        for _ in 0..len {
+            let options: Vec<Move> = Move::ALL
+                .into_iter()
+                .filter(|&dir| self.is_legal(dir) && Some(dir.inverse()) != last)
+                .collect();
+            if options.is_empty() {
+                break;
+            }
+            let dir = options[rng.below(options.len())];
+            let _ = self.perform_move(dir);
+            last = Some(dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reproducible() {
+        let a = Puzzle::scrambled(4, 4, Scramble::Uniform, 7).unwrap();
+        let b = Puzzle::scrambled(4, 4, Scramble::Uniform, 7).unwrap();
+        let c = Puzzle::scrambled(4, 4, Scramble::Uniform, 8).unwrap();
+        assert_eq!(a.tiles(), b.tiles());
+        assert_ne!(a.tiles(), c.tiles());
+    }
+
+    #[test]
+    fn test_always_solvable() {
+        for seed in 0..200 {
+            for (rows, cols) in [(1, 5), (2, 3), (3, 3), (4, 4), (3, 5)] {
+                assert!(
+                    Puzzle::scrambled(rows, cols, Scramble::Uniform, seed)
+                        .unwrap()
+                        .is_solvable()
+                );
+                assert!(
+                    Puzzle::scrambled(rows, cols, Scramble::Walk(30), seed)
+                        .unwrap()
+                        .is_solvable()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_walk_length() {
+        let table = DistanceTable::build(2, 3).unwrap();
+        let p = Puzzle::scrambled(2, 3, Scramble::Walk(3), 1).unwrap();
+        // no backtracking, and 3 moves cannot close a loop
+        assert_eq!(table.distance(&p), Some(3));
+    }
+}
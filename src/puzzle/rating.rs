@@ -0,0 +1,166 @@
+/*
+ * rating.rs
+ * Difficulty rating for generated content
+ * weighted sum of
+ *   - optimal distance, or the heuristic estimate when IDA* runs out of nodes
+ *   - best heuristic estimate
+ *   - length of the reduction solver's solution
+ *   - pathological pairs the reduction had to banish
+ * mapped to named tiers by score
+ */
+
+use crate::puzzle::*;
+
+/* Tier covering every score up to max_score */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tier {
+    pub name: String,
+    pub max_score: f64,
+}
+
+impl Tier {
+    pub fn new(name: &str, max_score: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            max_score,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RatingConfig {
+    pub distance_weight: f64,
+    pub heuristic_weight: f64,
+    pub reduction_weight: f64, // per move of the reduction solution
+    pub banish_weight: f64,
+    pub optimal_nodes: u64, // IDA* budget before falling back to the estimate
+    pub tiers: Vec<Tier>,   // ascending max_score, scores above the last go to the last
+}
+
+impl Default for RatingConfig {
+    fn default() -> Self {
+        Self {
+            distance_weight: 1.0,
+            heuristic_weight: 0.25,
+            reduction_weight: 0.05,
+            banish_weight: 2.0,
+            optimal_nodes: 1 << 21,
+            tiers: vec![
+                Tier::new("easy", 25.0),
+                Tier::new("medium", 60.0),
+                Tier::new("hard", f64::INFINITY),
+            ],
+        }
+    }
+}
+
+/* Everything that went into a score */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rating {
+    pub optimal: Option<u32>, // None when out of nodes
+    pub heuristic: u32,
+    pub reduction: usize,
+    pub banishes: usize,
+    pub score: f64,
+    pub tier: Option<String>, // None only without tiers
+}
+
+/* Rates boards of one size, heuristic tables are built once */
+pub struct Rater {
+    config: RatingConfig,
+    heuristic: MaxOf,
+}
+
+impl Rater {
+    pub fn new(rows: usize, cols: usize, config: RatingConfig) -> Self {
+        Self {
+            config,
+            heuristic: MaxOf::strongest(rows, cols),
+        }
+    }
+
+    pub fn config(&self) -> &RatingConfig {
+        &self.config
+    }
+
+    pub fn rate(&self, p: &Puzzle) -> Result<Rating, SolveError> {
+        let mut reduced = p.clone();
+        let reduction = reduced.solve()?.len();
+        let mut opts = SolveOptions {
+            max_nodes: Some(self.config.optimal_nodes),
+            ..SolveOptions::default()
+        };
+        let optimal = match p.solve_optimal_with(&mut opts) {
+            Ok(moves) => Some(moves.len() as u32),
+            Err(SolveError::NodeLimitExceeded { .. }) => None,
+            Err(err) => return Err(err),
+        };
+        let heuristic = self.heuristic.estimate(p);
+        let config = &self.config;
+        let score = config.distance_weight * optimal.unwrap_or(heuristic) as f64
+            + config.heuristic_weight * heuristic as f64
+            + config.reduction_weight * reduction as f64
+            + config.banish_weight * reduced.banished as f64;
+        let tier = config
+            .tiers
+            .iter()
+            .find(|tier| score <= tier.max_score)
+            .or(config.tiers.last())
+            .map(|tier| tier.name.clone());
+        Ok(Rating {
+            optimal,
+            heuristic,
+            reduction,
+            banishes: reduced.banished,
+            score,
+            tier,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tiers() {
+        let rater = Rater::new(4, 4, RatingConfig::default());
+        let goal = rater.rate(&Puzzle::goal(4, 4).unwrap()).unwrap();
+        assert_eq!(goal.score, 0.0);
+        assert_eq!(goal.tier.as_deref(), Some("easy"));
+        let near = Puzzle::scrambled(4, 4, Scramble::Walk(6), 1).unwrap();
+        let far = Puzzle::scrambled(4, 4, Scramble::Walk(60), 1).unwrap();
+        let (near, far) = (rater.rate(&near).unwrap(), rater.rate(&far).unwrap());
+        assert!(near.score < far.score);
+        assert!(near.optimal.unwrap() <= 6 && near.heuristic <= near.optimal.unwrap());
+        assert_eq!(near.tier.as_deref(), Some("easy"));
+        assert_ne!(far.tier.as_deref(), Some("easy"));
+        let p = Puzzle::new(vec![vec![2, 1], vec![3, 0]]).unwrap();
+        assert_eq!(
+            Rater::new(2, 2, RatingConfig::default()).rate(&p),
+            Err(SolveError::Unsolvable)
+        );
+    }
+
+    #[test]
+    fn test_fallbacks_and_banishes() {
+        // no node budget: the estimate stands in for the distance
+        let config = RatingConfig {
+            optimal_nodes: 0,
+            tiers: vec![Tier::new("all", 0.0)],
+            ..RatingConfig::default()
+        };
+        let rater = Rater::new(5, 5, config);
+        let p = Puzzle::scrambled(5, 5, Scramble::Uniform, 3).unwrap();
+        let rating = rater.rate(&p).unwrap();
+        assert_eq!(rating.optimal, None);
+        assert!(rating.heuristic > 0);
+        assert_eq!(rating.tier.as_deref(), Some("all"));
+        let rater = Rater::new(4, 4, rater.config().clone());
+        let banishes: usize = (0..40)
+            .map(|seed| Puzzle::scrambled(4, 4, Scramble::Uniform, seed).unwrap())
+            .map(|p| rater.rate(&p).unwrap().banishes)
+            .sum();
+        assert!(banishes > 0);
+    }
+}
@@ -0,0 +1,493 @@
+/*
+ * rotation.rs
+ * Rotation puzzles: no blank, a move turns the four tiles of any 2x2 zone
+ * one place round, the cycle the reduction solver makes with the blank
+ * tile t belongs in cell t - 1
+ * solvability
+ *   - a single row or column has no zone, only the goal
+ *   - 2x2, 2x3, 3x2: only some arrangements (4, 120 of 720), searched
+ *   - larger: a turn is a 4-cycle, an odd permutation, and overlapping zones
+ *     reach every arrangement
+ * solving: reduction over a shrinking window, as in solver_4x4.rs
+ *   - fix the top row or left column (the longer side), one tile at a time
+ *     through the free zones, the last two as a pair since only one zone
+ *     still reaches them
+ *   - once 8 cells are left: breadth first over their arrangements (a 2x3
+ *     window would reach too few)
+ */
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use super::solver_4x4::ZoneOrigin;
+use crate::puzzle::*;
+
+// a counter-clockwise turn moves each tile to the next position
+const RING: [ZonePos; 4] = [
+    ZonePos::TopLeft,
+    ZonePos::BottomLeft,
+    ZonePos::BottomRight,
+    ZonePos::TopRight,
+];
+
+// the last cells, from 2x4, 4x2 or a 3x3 less its corner, are solved breadth first
+const BASE_CELLS: usize = 8;
+
+// boards up to this many cells (2x2, 2x3, 3x2) reach only some arrangements
+const PARTIAL_CELLS: usize = 6;
+
+/* Way a zone turns */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Spin {
+    Clockwise,
+    CounterClockwise,
+}
+
+/* One move: turn the 2x2 zone with top-left cell `zone` */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Turn {
+    pub zone: ZoneOrigin,
+    pub spin: Spin,
+}
+
+impl Turn {
+    // turn that undoes this one
+    pub fn inverse(self) -> Turn {
+        let spin = match self.spin {
+            Spin::Clockwise => Spin::CounterClockwise,
+            Spin::CounterClockwise => Spin::Clockwise,
+        };
+        Turn { spin, ..self }
+    }
+}
+
+impl fmt::Display for Turn {
+    // zone origin then cw / ccw, e.g. "(1,2) ccw"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let spin = match self.spin {
+            Spin::Clockwise => "cw",
+            Spin::CounterClockwise => "ccw",
+        };
+        write!(f, "({},{}) {}", self.zone.0, self.zone.1, spin)
+    }
+}
+
+/* Board of a rotation puzzle */
+#[derive(Debug, Clone)]
+pub struct RotationPuzzle {
+    grid: Vec<u32>, // tiles 1..=rows*cols
+    rows: usize,
+    cols: usize,
+}
+
+impl RotationPuzzle {
+    pub fn new(in_grid: Vec<Vec<u32>>) -> Result<Self, PuzzleError> {
+        let rows = in_grid.len();
+        let cols = in_grid.first().map_or(0, |row| row.len());
+        for (r, row) in in_grid.iter().enumerate() {
+            if row.len() != cols {
+                return Err(PuzzleError::RaggedRow {
+                    row: r,
+                    len: row.len(),
+                    expected: cols,
+                });
+            }
+        }
+        Self::from_tiles(rows, cols, in_grid.concat())
+    }
+
+    // tiles must be exactly 1..=rows*cols, each once
+    pub fn from_tiles(rows: usize, cols: usize, tiles: Vec<u32>) -> Result<Self, PuzzleError> {
+        if rows == 0 || cols == 0 {
+            return Err(PuzzleError::EmptyBoard);
+        }
+        let mut seen = vec![false; rows * cols];
+        for &tile in &tiles {
+            match (tile as usize).checked_sub(1).and_then(|i| seen.get_mut(i)) {
+                Some(slot) if !*slot => *slot = true,
+                _ => return Err(PuzzleError::InvalidTiles),
+            }
+        }
+        if tiles.len() != rows * cols {
+            return Err(PuzzleError::InvalidTiles);
+        }
+        Ok(Self {
+            grid: tiles,
+            rows,
+            cols,
+        })
+    }
+
+    pub fn goal(rows: usize, cols: usize) -> Result<Self, PuzzleError> {
+        Self::from_tiles(rows, cols, (1..=(rows * cols) as u32).collect())
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn tiles(&self) -> &[u32] {
+        &self.grid
+    }
+
+    // origins of every zone, in reading order
+    pub fn zones(&self) -> Vec<ZoneOrigin> {
+        let (rows, cols) = (self.rows.saturating_sub(1), self.cols.saturating_sub(1));
+        (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (row, col)))
+            .collect()
+    }
+
+    pub fn is_legal(&self, turn: Turn) -> bool {
+        let (row, col) = turn.zone;
+        row < self.rows.saturating_sub(1) && col < self.cols.saturating_sub(1)
+    }
+
+    pub fn perform(&mut self, turn: Turn) -> Result<(), PuzzleError> {
+        if !self.is_legal(turn) {
+            let (row, col) = turn.zone;
+            return Err(PuzzleError::NoSuchZone { row, col });
+        }
+        let cells = self.zone_cells(turn.zone);
+        let tiles = cells.map(|cell| self.grid[cell]);
+        for (i, tile) in tiles.into_iter().enumerate() {
+            // ring order is counter-clockwise
+            let to = match turn.spin {
+                Spin::CounterClockwise => (i + 1) % 4,
+                Spin::Clockwise => (i + 3) % 4,
+            };
+            self.grid[cells[to]] = tile;
+        }
+        Ok(())
+    }
+
+    // cells of a zone in RING order
+    fn zone_cells(&self, (row, col): ZoneOrigin) -> [usize; 4] {
+        RING.each_ref().map(|pos| {
+            let (r, c) = pos.to_coords();
+            (row + r) * self.cols + col + c
+        })
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.grid
+            .iter()
+            .enumerate()
+            .all(|(i, &t)| t as usize == i + 1)
+    }
+
+    pub fn is_solvable(&self) -> bool {
+        if self.rows == 1 || self.cols == 1 {
+            return self.is_solved();
+        }
+        if self.grid.len() <= PARTIAL_CELLS {
+            // 2x3 and 3x2 reach only 120 of their 720 arrangements: search
+            return Reduction::new(self)
+                .finish(&mut SolveOptions::default())
+                .is_ok();
+        }
+        true
+    }
+
+    // Reduction, not the fewest turns
+    pub fn solve(&self) -> Result<Vec<Turn>, SolveError> {
+        self.solve_with(&mut SolveOptions::default())
+    }
+
+    pub fn solve_with(&self, opts: &mut SolveOptions) -> Result<Vec<Turn>, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        let mut reduction = Reduction::new(self);
+        if self.rows == 1 || self.cols == 1 {
+            return Ok(reduction.turns);
+        }
+        // window still to solve starts at (row, col)
+        let (mut row, mut col) = (0, 0);
+        'reduce: loop {
+            let (height, width) = (self.rows - row, self.cols - col);
+            opts.checkpoint(Progress::nodes(reduction.nodes, None))?;
+            // top row or left column, the longer side first
+            let line: Vec<usize> = if height >= width {
+                (col..self.cols).map(|c| row * self.cols + c).collect()
+            } else {
+                (row..self.rows).map(|r| r * self.cols + col).collect()
+            };
+            let mut i = 0;
+            while i < line.len() {
+                if reduction.free_cells() <= BASE_CELLS {
+                    break 'reduce;
+                }
+                // only one zone reaches the last two cells of a line: place them together
+                let take = if line.len() - i == 2 { 2 } else { 1 };
+                reduction.place(&line[i..i + take], opts)?;
+                i += take;
+            }
+            if height >= width {
+                row += 1;
+            } else {
+                col += 1;
+            }
+        }
+        reduction.finish(opts)?;
+        if !reduction.board.is_solved() {
+            return Err(SolveError::SearchExhausted);
+        }
+        Ok(reduction.turns)
+    }
+}
+
+/* Board being reduced, with the cells already fixed */
+struct Reduction {
+    board: RotationPuzzle,
+    free: Vec<bool>,
+    turns: Vec<Turn>,
+    nodes: u64,
+}
+
+impl Reduction {
+    fn new(board: &RotationPuzzle) -> Self {
+        Self {
+            board: board.clone(),
+            free: vec![true; board.grid.len()],
+            turns: Vec::new(),
+            nodes: 0,
+        }
+    }
+
+    fn free_cells(&self) -> usize {
+        self.free.iter().filter(|&&free| free).count()
+    }
+
+    // turns of the zones that hold no fixed cell
+    fn free_turns(&self) -> Vec<(Turn, [usize; 4])> {
+        let mut turns = Vec::new();
+        for zone in self.board.zones() {
+            let cells = self.board.zone_cells(zone);
+            if cells.iter().all(|&cell| self.free[cell]) {
+                for spin in [Spin::CounterClockwise, Spin::Clockwise] {
+                    turns.push((Turn { zone, spin }, cells));
+                }
+            }
+        }
+        turns
+    }
+
+    // Bring the goal tiles of `cells` home, breadth first over where those
+    // tiles are (the other free tiles may go anywhere), then fix them
+    fn place(&mut self, cells: &[usize], opts: &mut SolveOptions) -> Result<(), SolveError> {
+        let turns = self.free_turns();
+        let start: Vec<usize> = cells
+            .iter()
+            .map(|&cell| {
+                let tile = cell as u32 + 1;
+                self.board.grid.iter().position(|&t| t == tile).unwrap_or(0)
+            })
+            .collect();
+        let mut parent: HashMap<Vec<usize>, (Vec<usize>, Turn)> = HashMap::new();
+        let mut seen = HashSet::from([start.clone()]);
+        let mut queue = VecDeque::from([start.clone()]);
+        let mut found = false;
+        while let Some(at) = queue.pop_front() {
+            self.nodes += 1;
+            opts.tick(self.nodes, None)?;
+            if at == cells {
+                found = true;
+                break;
+            }
+            for (turn, ring) in &turns {
+                let mut to = at.clone();
+                for cell in to.iter_mut() {
+                    if let Some(i) = ring.iter().position(|c| c == cell) {
+                        *cell = match turn.spin {
+                            Spin::CounterClockwise => ring[(i + 1) % 4],
+                            Spin::Clockwise => ring[(i + 3) % 4],
+                        };
+                    }
+                }
+                if seen.insert(to.clone()) {
+                    parent.insert(to.clone(), (at.clone(), *turn));
+                    queue.push_back(to);
+                }
+            }
+        }
+        if !found {
+            return Err(SolveError::SearchExhausted);
+        }
+        let mut path = Vec::new();
+        let mut at = cells.to_vec();
+        while at != start {
+            let (from, turn) = parent[&at].clone();
+            path.push(turn);
+            at = from;
+        }
+        for turn in path.into_iter().rev() {
+            self.play(turn)?;
+        }
+        for &cell in cells {
+            self.free[cell] = false;
+        }
+        Ok(())
+    }
+
+    // Breadth first over the arrangements of the cells still free
+    fn finish(&mut self, opts: &mut SolveOptions) -> Result<(), SolveError> {
+        let cells: Vec<usize> = (0..self.free.len()).filter(|&i| self.free[i]).collect();
+        // each turn as a permutation of places in the arrangement
+        let turns: Vec<(Turn, [usize; 4])> = self
+            .free_turns()
+            .into_iter()
+            .map(|(turn, ring)| (turn, ring.map(|c| cells.binary_search(&c).unwrap_or(0))))
+            .collect();
+        let start: Vec<u32> = cells.iter().map(|&c| self.board.grid[c]).collect();
+        let goal: Vec<u32> = cells.iter().map(|&c| c as u32 + 1).collect();
+        // (arrangement, parent, turn from parent)
+        let mut nodes = vec![(start.clone(), usize::MAX, None)];
+        let mut seen = HashSet::from([start]);
+        let mut queue = VecDeque::from([0]);
+        while let Some(i) = queue.pop_front() {
+            self.nodes += 1;
+            opts.tick(self.nodes, None)?;
+            if nodes[i].0 == goal {
+                let mut path = Vec::new();
+                let mut at = i;
+                while let (_, parent, Some(turn)) = nodes[at] {
+                    path.push(turn);
+                    at = parent;
+                }
+                for turn in path.into_iter().rev() {
+                    self.play(turn)?;
+                }
+                return Ok(());
+            }
+            for &(turn, ring) in &turns {
+                let mut next = nodes[i].0.clone();
+                for (k, &place) in ring.iter().enumerate() {
+                    let to = match turn.spin {
+                        Spin::CounterClockwise => ring[(k + 1) % 4],
+                        Spin::Clockwise => ring[(k + 3) % 4],
+                    };
+                    next[to] = nodes[i].0[place];
+                }
+                if seen.insert(next.clone()) {
+                    queue.push_back(nodes.len());
+                    nodes.push((next, i, Some(turn)));
+                }
+            }
+        }
+        Err(SolveError::SearchExhausted)
+    }
+
+    fn play(&mut self, turn: Turn) -> Result<(), SolveError> {
+        self.board
+            .perform(turn)
+            .map_err(|_| SolveError::SearchExhausted)?;
+        self.turns.push(turn);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::puzzle::random::Rng;
+
+    fn shuffled(rows: usize, cols: usize, seed: u64) -> RotationPuzzle {
+        let mut rng = Rng::new(seed);
+        let mut tiles = RotationPuzzle::goal(rows, cols).unwrap().tiles().to_vec();
+        for i in (1..tiles.len()).rev() {
+            tiles.swap(i, rng.below(i + 1));
+        }
+        RotationPuzzle::from_tiles(rows, cols, tiles).unwrap()
+    }
+
+    #[test]
+    fn test_turns() {
+        let mut p = RotationPuzzle::goal(2, 3).unwrap();
+        let turn = Turn {
+            zone: (0, 1),
+            spin: Spin::CounterClockwise,
+        };
+        p.perform(turn).unwrap();
+        assert_eq!(p.tiles(), &[1, 3, 6, 4, 2, 5]);
+        assert_eq!(turn.to_string(), "(0,1) ccw");
+        p.perform(turn.inverse()).unwrap();
+        assert!(p.is_solved());
+        assert_eq!(p.zones(), vec![(0, 0), (0, 1)]);
+        assert_eq!(
+            p.perform(Turn {
+                zone: (1, 0),
+                spin: Spin::Clockwise
+            }),
+            Err(PuzzleError::NoSuchZone { row: 1, col: 0 })
+        );
+        assert_eq!(
+            p.perform(Turn {
+                zone: (usize::MAX, 0),
+                spin: Spin::Clockwise
+            }),
+            Err(PuzzleError::NoSuchZone {
+                row: usize::MAX,
+                col: 0
+            })
+        );
+        assert_eq!(
+            RotationPuzzle::new(vec![vec![1, 2], vec![3, 3]]).unwrap_err(),
+            PuzzleError::InvalidTiles
+        );
+    }
+
+    #[test]
+    fn test_solvability_rule() {
+        for (rows, cols) in [(1, 3), (2, 2), (2, 3), (3, 2), (2, 4)] {
+            let start = RotationPuzzle::goal(rows, cols).unwrap();
+            let mut seen = HashSet::from([start.tiles().to_vec()]);
+            let mut stack = vec![start];
+            while let Some(p) = stack.pop() {
+                for zone in p.zones() {
+                    let mut next = p.clone();
+                    next.perform(Turn {
+                        zone,
+                        spin: Spin::CounterClockwise,
+                    })
+                    .unwrap();
+                    if seen.insert(next.tiles().to_vec()) {
+                        stack.push(next);
+                    }
+                }
+            }
+            let cells = rows * cols;
+            for r in 0..crate::puzzle::tables::factorial(cells) {
+                let tiles = crate::puzzle::tables::unrank(r, cells);
+                let tiles = tiles.iter().map(|&t| t + 1).collect();
+                let p = RotationPuzzle::from_tiles(rows, cols, tiles).unwrap();
+                assert_eq!(p.is_solvable(), seen.contains(p.tiles()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_solves_boards() {
+        for (rows, cols) in [(2, 2), (2, 3), (3, 3), (4, 4), (2, 6), (5, 3)] {
+            for seed in 0..4 {
+                let p = shuffled(rows, cols, seed);
+                match p.solve() {
+                    Ok(turns) => {
+                        let mut replay = p.clone();
+                        for turn in turns {
+                            replay.perform(turn).unwrap();
+                        }
+                        assert!(replay.is_solved());
+                    }
+                    Err(err) => assert!(!p.is_solvable() && err == SolveError::Unsolvable),
+                }
+            }
+        }
+        let line = RotationPuzzle::new(vec![vec![2, 1, 3]]).unwrap();
+        assert_eq!(line.solve(), Err(SolveError::Unsolvable));
+    }
+}
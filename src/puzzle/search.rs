@@ -0,0 +1,364 @@
+/*
+ * search.rs
+ * Heuristic searches for boards too big for an optimal solver
+ * beam search guided by any Evaluator
+ * weighted A* on Manhattan distance, at most `weight` times optimal
+ */
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::puzzle::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeamConfig {
+    pub width: usize,      // states kept per depth
+    pub max_depth: usize,  // give up after this many moves
+    pub max_states: usize, // boards remembered for duplicate detection
+}
+
+impl Default for BeamConfig {
+    fn default() -> Self {
+        Self {
+            width: 1000,
+            max_depth: 10_000,
+            max_states: 1 << 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightedConfig {
+    pub weight: f64,       // f = g + weight * h, at least 1
+    pub max_states: usize, // give up once this many boards are stored
+}
+
+impl Default for WeightedConfig {
+    fn default() -> Self {
+        Self {
+            weight: 2.0,
+            max_states: 1 << 20,
+        }
+    }
+}
+
+/* Solution plus how much work it took */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchReport {
+    pub moves: Vec<Move>,
+    pub expanded: u64,      // states whose successors were generated
+    pub bound: Option<f64>, // solution is at most this factor times optimal
+}
+
+impl SearchReport {
+    pub fn length(&self) -> usize {
+        self.moves.len()
+    }
+}
+
+// parent of the search tree's top nodes
+pub(crate) const ROOT: usize = usize::MAX;
+
+// search tree shared by every node: (parent, move from parent)
+pub(crate) struct Tree<M>(pub(crate) Vec<(usize, M)>);
+
+impl<M: Copy> Tree<M> {
+    pub(crate) fn add(&mut self, parent: usize, dir: M) -> usize {
+        self.0.push((parent, dir));
+        self.0.len() - 1
+    }
+
+    pub(crate) fn path(&self, mut node: usize) -> Vec<M> {
+        let mut moves = Vec::new();
+        while node != ROOT {
+            let (parent, dir) = self.0[node];
+            moves.push(dir);
+            node = parent;
+        }
+        moves.reverse();
+        moves
+    }
+}
+
+impl Puzzle {
+    // Beam search: keep the `width` best-scored states of each depth
+    pub fn solve_beam(
+        &self,
+        config: &BeamConfig,
+        evaluator: &dyn Evaluator,
+    ) -> Result<SearchReport, SolveError> {
+        self.solve_beam_with(config, evaluator, &mut SolveOptions::default())
+    }
+
+    pub fn solve_beam_with(
+        &self,
+        config: &BeamConfig,
+        evaluator: &dyn Evaluator,
+        opts: &mut SolveOptions,
+    ) -> Result<SearchReport, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        let mut report = SearchReport {
+            moves: Vec::new(),
+            expanded: 0,
+            bound: None,
+        };
+        if self.is_solved() {
+            return Ok(report);
+        }
+        let mut tree = Tree(Vec::new());
+        let mut seen = HashSet::from([self.grid.clone()]);
+        let mut beam = vec![(self.clone(), ROOT, None)];
+        for depth in 0..config.max_depth {
+            // the bound reported is the depth reached so far
+            opts.checkpoint(Progress::nodes(report.expanded, Some(depth as u32)))?;
+            let mut children = Vec::new();
+            for (p, node, last) in &beam {
+                report.expanded += 1;
+                for dir in Move::ALL {
+                    if Some(dir.inverse()) == *last {
+                        continue;
+                    }
+                    let mut child = p.clone();
+                    if child.perform_move(dir).is_err() || !seen.insert(child.grid.clone()) {
+                        continue;
+                    }
+                    let child_node = tree.add(*node, dir);
+                    if child.is_solved() {
+                        report.moves = tree.path(child_node);
+                        return Ok(report);
+                    }
+                    children.push((evaluator.evaluate(&child), child, child_node, Some(dir)));
+                }
+            }
+            if children.is_empty() {
+                break;
+            }
+            children.sort_by(|a, b| a.0.total_cmp(&b.0));
+            children.truncate(config.width);
+            beam = children
+                .into_iter()
+                .map(|(_, p, node, last)| (p, node, last))
+                .collect();
+            // over the memory cap: only remember the current beam
+            if seen.len() > config.max_states {
+                seen = beam.iter().map(|(p, _, _)| p.grid.clone()).collect();
+            }
+        }
+        Err(SolveError::SearchExhausted)
+    }
+
+    // Weighted A*: f = g + weight * manhattan, never reopens a closed board
+    pub fn solve_weighted(&self, config: &WeightedConfig) -> Result<SearchReport, SolveError> {
+        self.solve_weighted_with(config, &mut SolveOptions::default())
+    }
+
+    pub fn solve_weighted_with(
+        &self,
+        config: &WeightedConfig,
+        opts: &mut SolveOptions,
+    ) -> Result<SearchReport, SolveError> {
+        self.solve_weighted_within(config, usize::MAX, opts)
+    }
+
+    // Weighted A* never storing a board Manhattan distance puts out of
+    // reach within `budget` moves
+    pub(crate) fn solve_weighted_within(
+        &self,
+        config: &WeightedConfig,
+        budget: usize,
+        opts: &mut SolveOptions,
+    ) -> Result<SearchReport, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        let weight = config.weight.max(1.0);
+        let mut report = SearchReport {
+            moves: Vec::new(),
+            expanded: 0,
+            bound: Some(weight),
+        };
+        let mut tree = Tree(Vec::new());
+        let mut cut = false; // dropped a board for the budget
+        let mut best_g = HashMap::from([(self.grid.clone(), 0)]);
+        let mut closed = HashSet::new();
+        let mut open = BinaryHeap::from([Open {
+            f: weight * self.manhattan() as f64,
+            g: 0,
+            node: ROOT,
+            puzzle: self.clone(),
+        }]);
+        while let Some(Open {
+            g, node, puzzle, ..
+        }) = open.pop()
+        {
+            if puzzle.is_solved() {
+                report.moves = tree.path(node);
+                return Ok(report);
+            }
+            if !closed.insert(puzzle.grid.clone()) {
+                continue;
+            }
+            report.expanded += 1;
+            opts.tick(report.expanded, None)?;
+            for dir in Move::ALL {
+                let mut child = puzzle.clone();
+                if child.perform_move(dir).is_err() || closed.contains(&child.grid) {
+                    continue;
+                }
+                if best_g.get(&child.grid).is_some_and(|&best| best <= g + 1) {
+                    continue;
+                }
+                let h = child.manhattan();
+                if (g + 1 + h) as usize > budget {
+                    cut = true;
+                    continue;
+                }
+                if best_g.len() >= config.max_states {
+                    return Err(SolveError::SearchExhausted);
+                }
+                best_g.insert(child.grid.clone(), g + 1);
+                open.push(Open {
+                    f: (g + 1) as f64 + weight * h as f64,
+                    g: g + 1,
+                    node: tree.add(node, dir),
+                    puzzle: child,
+                });
+            }
+        }
+        Err(match cut {
+            true => SolveError::BudgetExceeded { budget },
+            false => SolveError::SearchExhausted,
+        })
+    }
+}
+
+/* Open list entry, smallest f first, deeper first on ties */
+pub(crate) struct Open<P> {
+    pub(crate) f: f64,
+    pub(crate) g: u32,
+    pub(crate) node: usize,
+    pub(crate) puzzle: P,
+}
+
+impl<P> PartialEq for Open<P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<P> Eq for Open<P> {}
+
+impl<P> PartialOrd for Open<P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<P> Ord for Open<P> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.f.total_cmp(&self.f).then(self.g.cmp(&other.g))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::puzzle::model::test::manhattan_model;
+
+    fn replay(p: &Puzzle, moves: &[Move]) -> bool {
+        let mut p = p.clone();
+        moves.iter().all(|&dir| p.perform_move(dir).is_ok()) && p.is_solved()
+    }
+
+    #[test]
+    fn test_beam_5x5() {
+        let model = manhattan_model(5, 5);
+        let config = BeamConfig {
+            width: 500,
+            ..BeamConfig::default()
+        };
+        let p = Puzzle::scrambled(5, 5, Scramble::Uniform, 11).unwrap();
+        let report = p.solve_beam(&config, &model).unwrap();
+        assert!(replay(&p, &report.moves));
+        // shorter than the mechanical solution
+        let reduction = p.clone().solve().unwrap();
+        assert!(report.moves.len() < reduction.len());
+    }
+
+    #[test]
+    fn test_beam_limits() {
+        let model = manhattan_model(3, 3);
+        let p = Puzzle::scrambled(3, 3, Scramble::Uniform, 5).unwrap();
+        let config = BeamConfig {
+            width: 4,
+            max_depth: 2,
+            ..BeamConfig::default()
+        };
+        assert_eq!(
+            p.solve_beam(&config, &model),
+            Err(SolveError::SearchExhausted)
+        );
+        let p = Puzzle::new(vec![vec![2, 1], vec![3, 0]]).unwrap();
+        assert_eq!(
+            p.solve_beam(&BeamConfig::default(), &model),
+            Err(SolveError::Unsolvable)
+        );
+        let goal = Puzzle::goal(3, 3).unwrap();
+        let report = goal.solve_beam(&BeamConfig::default(), &model).unwrap();
+        assert!(report.moves.is_empty());
+        // a tiny memory cap forgets old boards but keeps searching
+        let capped = BeamConfig {
+            width: 50,
+            max_states: 100,
+            ..BeamConfig::default()
+        };
+        let p = Puzzle::scrambled(3, 3, Scramble::Uniform, 5).unwrap();
+        assert!(p.solve_beam(&capped, &model).is_ok());
+    }
+
+    #[test]
+    fn test_weighted_bound() {
+        let table = DistanceTable::build(3, 3).unwrap();
+        for weight in [1.0, 1.5, 3.0] {
+            let config = WeightedConfig {
+                weight,
+                ..WeightedConfig::default()
+            };
+            for seed in 0..10 {
+                let p = Puzzle::scrambled(3, 3, Scramble::Uniform, seed).unwrap();
+                let report = p.solve_weighted(&config).unwrap();
+                let optimal = table.distance(&p).unwrap() as f64;
+                assert!(report.length() as f64 <= weight * optimal);
+                if weight == 1.0 {
+                    assert_eq!(report.length() as f64, optimal);
+                }
+                let mut replay = p.clone();
+                for dir in report.moves {
+                    replay.perform_move(dir).unwrap();
+                }
+                assert!(replay.is_solved());
+            }
+        }
+    }
+
+    #[test]
+    fn test_weighted_large_boards() {
+        let config = WeightedConfig {
+            weight: 3.0,
+            ..WeightedConfig::default()
+        };
+        let p = Puzzle::scrambled(4, 6, Scramble::Walk(60), 2).unwrap();
+        let report = p.solve_weighted(&config).unwrap();
+        // the walk length bounds the optimum from above
+        assert!(report.length() <= 3 * 60 && report.expanded > 0);
+        assert_eq!(report.bound, Some(3.0));
+        let capped = WeightedConfig {
+            weight: 1.0,
+            max_states: 50,
+        };
+        let p = Puzzle::scrambled(5, 5, Scramble::Uniform, 2).unwrap();
+        assert_eq!(p.solve_weighted(&capped), Err(SolveError::SearchExhausted));
+    }
+}
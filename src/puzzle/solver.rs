@@ -0,0 +1,178 @@
+/*
+ * solver.rs
+ * Public entry point shared by every board size
+ * move accounting and error reporting for the reduction solver
+ */
+
+use crate::puzzle::*;
+
+// blank cycling a 2x2 zone brings it back to its starting arrangement after 12 moves
+pub(crate) const CYCLE_PERIOD: usize = 12;
+
+impl Puzzle {
+    // Solve with no limit on the number of moves
+    pub fn solve(&mut self) -> Result<Vec<Move>, SolveError> {
+        self.solve_with_budget(usize::MAX)
+    }
+
+    // Solve, giving up once the solution would need more than `budget` moves
+    pub fn solve_with_budget(&mut self, budget: usize) -> Result<Vec<Move>, SolveError> {
+        self.solve_budgeted(budget, &mut SolveOptions::default())
+    }
+
+    // Solve under cancellation, deadline and node (here: move) limits
+    pub fn solve_with(&mut self, opts: &mut SolveOptions) -> Result<Vec<Move>, SolveError> {
+        self.solve_budgeted(usize::MAX, opts)
+    }
+
+    fn solve_budgeted(
+        &mut self,
+        budget: usize,
+        opts: &mut SolveOptions,
+    ) -> Result<Vec<Move>, SolveError> {
+        // check global solvability (only once)
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        self.banished = 0;
+        if self.walled {
+            // the reduction needs the full rectangle
+            return self.solve_walled(budget, opts);
+        }
+        self.budget = budget;
+        self.solution.clear();
+        // on a torus only laps to fix the parity, the reduction stays on the flat board
+        let topology = self.topology;
+        let mut result = match topology {
+            Topology::Torus => self.lap_to_flat_parity(),
+            Topology::Flat => Ok(()),
+        };
+        self.topology = Topology::Flat;
+        if result.is_ok() {
+            result = if self.rows == 1 || self.cols == 1 {
+                self.checkpoint(Phase::Line, opts)
+                    .and_then(|_| self.solve_line())
+            } else {
+                self.solve_window(opts)
+            };
+        }
+        self.topology = topology;
+        // back to absolute coordinates, whether or not the solve finished
+        self.blank = (
+            self.blank.0 + self.row_offset,
+            self.blank.1 + self.col_offset,
+        );
+        self.row_offset = 0;
+        self.col_offset = 0;
+        self.budget = usize::MAX;
+        let moves = std::mem::take(&mut self.solution);
+        result?;
+        if !self.is_solved() {
+            return Err(self.violation("board not solved after reduction"));
+        }
+        Ok(moves)
+    }
+
+    // Single solver move, counted against the budget
+    pub(crate) fn step(&mut self, dir: Move) -> Result<(), SolveError> {
+        if self.solution.len() >= self.budget {
+            return Err(SolveError::BudgetExceeded {
+                budget: self.budget,
+            });
+        }
+        if self.perform_move(dir).is_err() {
+            return Err(self.violation("blank moved off the board"));
+        }
+        self.solution.push(dir);
+        Ok(())
+    }
+
+    // Drop the solved top row and/or left column from the active window
+    pub(crate) fn shrink_window(&mut self, row: bool, col: bool) -> Result<(), SolveError> {
+        if (row && self.blank.0 == 0) || (col && self.blank.1 == 0) {
+            return Err(self.violation("blank left behind in a solved line"));
+        }
+        if row {
+            self.row_offset += 1;
+            self.blank.0 -= 1;
+        }
+        if col {
+            self.col_offset += 1;
+            self.blank.1 -= 1;
+        }
+        Ok(())
+    }
+
+    // Enter a phase, reporting progress and honouring the stop conditions
+    pub(crate) fn checkpoint(
+        &mut self,
+        phase: Phase,
+        opts: &mut SolveOptions,
+    ) -> Result<(), SolveError> {
+        self.enter_phase(phase);
+        opts.checkpoint(Progress {
+            nodes: self.solution.len() as u64,
+            bound: None,
+            phase: Some(phase),
+            row_offset: self.row_offset,
+            col_offset: self.col_offset,
+        })
+    }
+
+    pub(crate) fn violation(&self, reason: &'static str) -> SolveError {
+        SolveError::InvariantViolated {
+            phase: self.phase,
+            reason,
+            snapshot: self.snapshot(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn grid_4x4() -> Vec<Vec<u32>> {
+        vec![
+            vec![1, 2, 0, 11],
+            vec![12, 7, 14, 9],
+            vec![3, 13, 4, 6],
+            vec![15, 10, 8, 5],
+        ]
+    }
+
+    #[test]
+    fn test_solution_replays() {
+        let mut p = Puzzle::new(grid_4x4()).unwrap();
+        let moves = p.solve().unwrap();
+        let mut replay = Puzzle::new(grid_4x4()).unwrap();
+        for dir in moves {
+            replay.perform_move(dir).unwrap();
+        }
+        assert!(replay.is_solved());
+        // board is usable again after solving
+        assert!(p.perform_move(Move::Up).is_ok());
+        assert_eq!(
+            p.perform_move(Move::Right),
+            Err(PuzzleError::IllegalMove(Move::Right))
+        );
+    }
+
+    #[test]
+    fn test_budget_exceeded() {
+        let mut p = Puzzle::new(grid_4x4()).unwrap();
+        assert_eq!(
+            p.solve_with_budget(10),
+            Err(SolveError::BudgetExceeded { budget: 10 })
+        );
+        let needed = Puzzle::new(grid_4x4()).unwrap().solve().unwrap().len();
+        let mut p = Puzzle::new(grid_4x4()).unwrap();
+        assert_eq!(p.solve_with_budget(needed).unwrap().len(), needed);
+    }
+
+    #[test]
+    fn test_unsolvable() {
+        let mut p = Puzzle::new(vec![vec![2, 1], vec![3, 0]]).unwrap();
+        assert_eq!(p.solve(), Err(SolveError::Unsolvable));
+    }
+}
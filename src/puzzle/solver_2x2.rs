@@ -7,27 +7,34 @@
 
 use crate::puzzle::Move;
 use crate::puzzle::Puzzle;
+use crate::puzzle::SolveError;
+
+use super::solver::CYCLE_PERIOD;
 
 impl Puzzle {
     pub(crate) fn is_solved_2x2(&self) -> bool {
         self.get(1, 1) == 0 && self.get(0, 0) < self.get(0, 1) && self.get(0, 1) < self.get(1, 0)
     }
 
-    pub(crate) fn solve_2x2(&mut self) {
-        while !self.is_solved_2x2() {
-            self.cycle_2x2();
+    pub(crate) fn solve_2x2(&mut self) -> Result<(), SolveError> {
+        for _ in 0..CYCLE_PERIOD {
+            if self.is_solved_2x2() {
+                return Ok(());
+            }
+            self.cycle_2x2()?;
         }
+        Err(self.violation("2x2 block never reached its goal"))
     }
 
-    fn cycle_2x2(&mut self) {
+    fn cycle_2x2(&mut self) -> Result<(), SolveError> {
         let dir = match self.get_blank() {
             (0, 0) => Move::Right,
             (0, 1) => Move::Down,
             (1, 0) => Move::Up,
             (1, 1) => Move::Left,
-            (_, _) => panic!("Oh no, out of bounds !!"),
+            (_, _) => return Err(self.violation("blank outside the 2x2 block")),
         };
-        self.perform_move(dir);
+        self.step(dir)
     }
 }
 
@@ -55,10 +62,10 @@ mod test {
     #[test]
     fn test_solved() {
         let grid = vec![vec![1, 2], vec![3, 0]];
-        let mut p = Puzzle::new(grid);
+        let mut p = Puzzle::new(grid).unwrap();
         assert!(p.is_solvable());
         assert!(p.is_solved());
-        p.solve_2x2();
+        p.solve_2x2().unwrap();
         assert!(p.is_solved());
 
         dbg!(p);
@@ -70,12 +77,22 @@ mod test {
         let mut pass = 0;
         for perm in perms {
             let grid = perm.chunks(2).map(|chunk| chunk.to_vec()).collect();
-            let mut p = Puzzle::new(grid);
+            let mut p = Puzzle::new(grid).unwrap();
             if p.is_solvable() {
-                p.solve_2x2();
+                p.solve_2x2().unwrap();
                 pass += if p.is_solved() { 1 } else { 0 };
             }
         }
         assert_eq!(pass, 12);
     }
+
+    #[test]
+    fn test_unsolvable_block() {
+        // reached only through a broken invariant, must not spin forever
+        let mut p = Puzzle::new(vec![vec![2, 1], vec![3, 0]]).unwrap();
+        assert!(matches!(
+            p.solve_2x2(),
+            Err(SolveError::InvariantViolated { .. })
+        ));
+    }
 }
@@ -0,0 +1,86 @@
+/* Degenerate case - solving a 1xN or Nx1 board */
+/* setup
+ * the blank can only slide along the line, so tiles never change order
+ * if solvable, sliding the blank to the far end solves it
+ *
+ */
+
+use crate::puzzle::Move;
+use crate::puzzle::Phase;
+use crate::puzzle::Puzzle;
+use crate::puzzle::SolveError;
+
+impl Puzzle {
+    pub(crate) fn solve_line(&mut self) -> Result<(), SolveError> {
+        self.enter_phase(Phase::Line);
+        if self.rows == 1 {
+            while self.get_blank().1 + 1 < self.cols {
+                self.step(Move::Right)?;
+            }
+        } else {
+            while self.get_blank().0 + 1 < self.rows {
+                self.step(Move::Down)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::puzzle::PuzzleError;
+
+    #[test]
+    fn test_row() {
+        let mut p = Puzzle::new(vec![vec![1, 0, 2, 3]]).unwrap();
+        assert!(p.is_solvable());
+        assert_eq!(p.solve().unwrap().len(), 2);
+        assert_eq!(p.grid, vec![1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn test_col() {
+        let mut p = Puzzle::new(vec![vec![0], vec![1], vec![2]]).unwrap();
+        assert!(p.is_solvable());
+        assert!(p.solve().is_ok());
+        assert_eq!(p.grid, vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_single_cell() {
+        let mut p = Puzzle::new(vec![vec![0]]).unwrap();
+        assert!(p.is_solved());
+        assert!(p.solve().is_ok());
+    }
+
+    #[test]
+    fn test_unsolvable_line() {
+        let mut p = Puzzle::new(vec![vec![2, 0, 1]]).unwrap();
+        assert!(!p.is_solvable());
+        assert_eq!(p.solve(), Err(SolveError::Unsolvable));
+        let mut p = Puzzle::new(vec![vec![2], vec![1], vec![0]]).unwrap();
+        assert_eq!(p.solve(), Err(SolveError::Unsolvable));
+    }
+
+    #[test]
+    fn test_bad_boards() {
+        assert_eq!(Puzzle::new(vec![]).unwrap_err(), PuzzleError::EmptyBoard);
+        assert_eq!(
+            Puzzle::new(vec![vec![]]).unwrap_err(),
+            PuzzleError::EmptyBoard
+        );
+        assert_eq!(
+            Puzzle::new(vec![vec![1, 2], vec![0]]).unwrap_err(),
+            PuzzleError::RaggedRow {
+                row: 1,
+                len: 1,
+                expected: 2
+            }
+        );
+        assert_eq!(
+            Puzzle::new(vec![vec![1, 1], vec![0, 3]]).unwrap_err(),
+            PuzzleError::InvalidTiles
+        );
+    }
+}
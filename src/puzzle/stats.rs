@@ -0,0 +1,127 @@
+/*
+ * stats.rs
+ * Facts about the whole state space of a small board
+ * distance distribution, diameter (God's number) and antipodal states
+ * CSV / JSON output for charting
+ */
+
+use crate::puzzle::tables::unrank;
+use crate::puzzle::*;
+
+/* Summary of a DistanceTable */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateSpaceStats {
+    pub rows: usize,
+    pub cols: usize,
+    pub counts: Vec<u64>,         // number of states at each depth
+    pub diameter: usize,          // largest optimal distance
+    pub antipodes: Vec<Vec<u32>>, // examples at the largest distance, row-major tiles
+}
+
+impl StateSpaceStats {
+    // build the table for rows x cols and summarise it
+    pub fn compute(rows: usize, cols: usize, max_examples: usize) -> Result<Self, TableError> {
+        Ok(Self::from_table(
+            &DistanceTable::build(rows, cols)?,
+            max_examples,
+        ))
+    }
+
+    pub fn from_table(table: &DistanceTable, max_examples: usize) -> Self {
+        let mut counts = Vec::new();
+        for &d in table.distances() {
+            if d == UNREACHABLE {
+                continue;
+            }
+            if counts.len() <= d as usize {
+                counts.resize(d as usize + 1, 0);
+            }
+            counts[d as usize] += 1;
+        }
+        let diameter = counts.len().saturating_sub(1);
+        let cells = table.rows() * table.cols();
+        let antipodes = table
+            .distances()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &d)| d as usize == diameter)
+            .take(max_examples)
+            .map(|(r, _)| unrank(r, cells))
+            .collect();
+        Self {
+            rows: table.rows(),
+            cols: table.cols(),
+            counts,
+            diameter,
+            antipodes,
+        }
+    }
+
+    // number of solvable states
+    pub fn states(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    // one `depth,states` line per depth
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("depth,states\n");
+        for (depth, count) in self.counts.iter().enumerate() {
+            out += &format!("{},{}\n", depth, count);
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let counts: Vec<String> = self.counts.iter().map(|c| c.to_string()).collect();
+        let antipodes: Vec<String> = self
+            .antipodes
+            .iter()
+            .map(|tiles| {
+                let tiles: Vec<String> = tiles.iter().map(|t| t.to_string()).collect();
+                format!("[{}]", tiles.join(","))
+            })
+            .collect();
+        format!(
+            "{{\"rows\":{},\"cols\":{},\"states\":{},\"diameter\":{},\"counts\":[{}],\"antipodes\":[{}]}}",
+            self.rows,
+            self.cols,
+            self.states(),
+            self.diameter,
+            counts.join(","),
+            antipodes.join(",")
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_2x2_stats() {
+        let stats = StateSpaceStats::compute(2, 2, 4).unwrap();
+        assert_eq!(stats.counts, vec![1, 2, 2, 2, 2, 2, 1]);
+        assert_eq!(stats.diameter, 6);
+        assert_eq!(stats.antipodes, vec![vec![0, 3, 2, 1]]);
+        assert_eq!(
+            stats.to_csv(),
+            "depth,states\n0,1\n1,2\n2,2\n3,2\n4,2\n5,2\n6,1\n"
+        );
+        assert_eq!(
+            stats.to_json(),
+            "{\"rows\":2,\"cols\":2,\"states\":12,\"diameter\":6,\
+             \"counts\":[1,2,2,2,2,2,1],\"antipodes\":[[0,3,2,1]]}"
+        );
+    }
+
+    #[test]
+    fn test_2x3_gods_number() {
+        let stats = StateSpaceStats::compute(2, 3, 1).unwrap();
+        assert_eq!(stats.states(), 360);
+        assert_eq!(stats.diameter, 21);
+        // antipodes really are that far away
+        let table = DistanceTable::build(2, 3).unwrap();
+        let p = Puzzle::from_tiles(2, 3, stats.antipodes[0].clone()).unwrap();
+        assert_eq!(table.distance(&p), Some(21));
+    }
+}
@@ -0,0 +1,409 @@
+/*
+ * tables.rs
+ * Exact distance tables for small boards (2x3, 3x2, 2x4, 3x3, ...)
+ * BFS from the goal over every state, indexed by permutation rank
+ * answers optimal distance / next move queries in constant time
+ * a table only knows distances to the goal, shortest paths between two
+ * arbitrary boards come from a bidirectional BFS meeting in the middle
+ */
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::puzzle::*;
+
+// 10! states is the largest table we are willing to hold
+pub const MAX_TABLE_CELLS: usize = 10;
+pub const UNREACHABLE: u8 = u8::MAX;
+
+const MAGIC: &[u8; 4] = b"P15T";
+const VERSION: u8 = 1;
+
+#[derive(Debug)]
+pub enum TableError {
+    TooLarge { cells: usize },
+    EmptyBoard,
+    Io(std::io::Error),
+    BadFormat(&'static str),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::TooLarge { cells } => write!(
+                f,
+                "{} cells is too many for a table (max {})",
+                cells, MAX_TABLE_CELLS
+            ),
+            TableError::EmptyBoard => write!(f, "board has no cells"),
+            TableError::Io(err) => write!(f, "table io failed: {}", err),
+            TableError::BadFormat(reason) => write!(f, "bad table file: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+impl From<std::io::Error> for TableError {
+    fn from(err: std::io::Error) -> Self {
+        TableError::Io(err)
+    }
+}
+
+/* Distance to the goal of every state of a rows x cols board */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistanceTable {
+    rows: usize,
+    cols: usize,
+    dist: Vec<u8>, // by permutation rank, UNREACHABLE for the other parity
+}
+
+impl DistanceTable {
+    pub fn build(rows: usize, cols: usize) -> Result<Self, TableError> {
+        let cells = rows * cols;
+        if cells == 0 {
+            return Err(TableError::EmptyBoard);
+        }
+        if cells > MAX_TABLE_CELLS {
+            return Err(TableError::TooLarge { cells });
+        }
+        let mut dist = vec![UNREACHABLE; factorial(cells)];
+        let goal = goal_tiles(cells);
+        dist[rank(&goal)] = 0;
+        let mut queue = VecDeque::from([rank(&goal)]);
+        while let Some(cur) = queue.pop_front() {
+            let d = dist[cur];
+            // expand with the same moves the board itself uses
+            let Ok(p) = Puzzle::from_tiles(rows, cols, unrank(cur, cells)) else {
+                continue;
+            };
+            for dir in Move::ALL {
+                let mut next = p.clone();
+                if next.perform_move(dir).is_err() {
+                    continue;
+                }
+                let next = rank(&next.grid);
+                if dist[next] == UNREACHABLE {
+                    dist[next] = d + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        Ok(Self { rows, cols, dist })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    // distance by permutation rank, UNREACHABLE for unsolvable states
+    pub fn distances(&self) -> &[u8] {
+        &self.dist
+    }
+
+    // optimal number of moves, None if the board is unsolvable, the wrong size or a torus
+    pub fn distance(&self, p: &Puzzle) -> Option<u8> {
+        if p.rows != self.rows || p.cols != self.cols || p.topology != Topology::Flat || p.walled {
+            return None;
+        }
+        match self.dist[rank(&p.grid)] {
+            UNREACHABLE => None,
+            d => Some(d),
+        }
+    }
+
+    // first move of some optimal solution, None if solved or unsolvable
+    pub fn next_move(&self, p: &Puzzle) -> Option<Move> {
+        let d = self.distance(p)?;
+        Move::ALL.into_iter().find(|&dir| {
+            let mut next = p.clone();
+            next.perform_move(dir).is_ok() && self.dist[rank(&next.grid)].checked_add(1) == Some(d)
+        })
+    }
+
+    // optimal solution by following next_move down to the goal
+    pub fn solve(&self, p: &Puzzle) -> Option<Vec<Move>> {
+        self.solve_with(p, &mut SolveOptions::default())
+            .ok()
+            .flatten()
+    }
+
+    // Same, a checkpoint per move; Ok(None) where solve gives None
+    pub fn solve_with(
+        &self,
+        p: &Puzzle,
+        opts: &mut SolveOptions,
+    ) -> Result<Option<Vec<Move>>, SolveError> {
+        let Some(d) = self.distance(p) else {
+            return Ok(None);
+        };
+        let mut cur = p.clone();
+        let mut moves = Vec::with_capacity(d as usize);
+        for _ in 0..d {
+            opts.checkpoint(Progress::nodes(moves.len() as u64, Some(d as u32)))?;
+            let Some(dir) = self.next_move(&cur) else {
+                return Ok(None);
+            };
+            if cur.perform_move(dir).is_err() {
+                return Ok(None);
+            }
+            moves.push(dir);
+        }
+        Ok(Some(moves))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TableError> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_to(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TableError> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    // layout: magic, version, rows, cols, then one byte per rank
+    pub fn write_to(&self, mut out: impl Write) -> Result<(), TableError> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION, self.rows as u8, self.cols as u8])?;
+        out.write_all(&self.dist)?;
+        Ok(())
+    }
+
+    pub fn read_from(mut input: impl Read) -> Result<Self, TableError> {
+        let mut header = [0u8; 7];
+        input.read_exact(&mut header)?;
+        if &header[0..4] != MAGIC {
+            return Err(TableError::BadFormat("wrong magic"));
+        }
+        if header[4] != VERSION {
+            return Err(TableError::BadFormat("unknown version"));
+        }
+        let (rows, cols) = (header[5] as usize, header[6] as usize);
+        let cells = rows * cols;
+        if cells == 0 || cells > MAX_TABLE_CELLS {
+            return Err(TableError::BadFormat("bad board size"));
+        }
+        let mut dist = Vec::with_capacity(factorial(cells));
+        input.read_to_end(&mut dist)?;
+        if dist.len() != factorial(cells) {
+            return Err(TableError::BadFormat("wrong number of entries"));
+        }
+        Ok(Self { rows, cols, dist })
+    }
+}
+
+pub(crate) fn goal_tiles(cells: usize) -> Vec<u32> {
+    (1..cells as u32).chain([0]).collect()
+}
+
+pub(crate) fn factorial(n: usize) -> usize {
+    (1..=n).product()
+}
+
+// Lehmer code of a permutation of 0..n
+pub(crate) fn rank(perm: &[u32]) -> usize {
+    let n = perm.len();
+    let mut r = 0;
+    for i in 0..n {
+        let smaller = perm[i + 1..].iter().filter(|&&t| t < perm[i]).count();
+        r = r * (n - i) + smaller;
+    }
+    r
+}
+
+pub(crate) fn unrank(mut r: usize, n: usize) -> Vec<u32> {
+    let mut digits = vec![0; n];
+    for i in (0..n).rev() {
+        digits[i] = r % (n - i);
+        r /= n - i;
+    }
+    let mut pool: Vec<u32> = (0..n as u32).collect();
+    digits.iter().map(|&d| pool.remove(d)).collect()
+}
+
+impl Puzzle {
+    // Shortest move sequence turning this board into `target`
+    pub fn path_between(&self, target: &Puzzle) -> Result<Vec<Move>, SolveError> {
+        self.path_between_with(target, &mut SolveOptions::default())
+    }
+
+    // Breadth first from both boards, a whole layer of the smaller frontier
+    // at a time; meant for state spaces small enough to table
+    pub fn path_between_with(
+        &self,
+        target: &Puzzle,
+        opts: &mut SolveOptions,
+    ) -> Result<Vec<Move>, SolveError> {
+        let shape = |p: &Puzzle| (p.rows, p.cols, p.topology, p.walled);
+        if shape(self) != shape(target) {
+            return Err(SolveError::Unsolvable);
+        }
+        // each side: board -> (move that reached it, depth)
+        let mut seen = [self, target].map(|p| HashMap::from([(p.grid.clone(), (None, 0))]));
+        let mut frontiers = [vec![self.clone()], vec![target.clone()]];
+        let mut nodes = 0;
+        let mut meet = (self.grid == target.grid).then(|| self.clone());
+        while meet.is_none() {
+            let side = usize::from(frontiers[1].len() < frontiers[0].len());
+            if frontiers[side].is_empty() {
+                return Err(SolveError::Unsolvable);
+            }
+            opts.checkpoint(Progress::nodes(nodes, None))?;
+            // finish the layer, the first meeting need not be the shortest
+            let mut best = u32::MAX;
+            let mut next = Vec::new();
+            for p in std::mem::take(&mut frontiers[side]) {
+                nodes += 1;
+                opts.tick(nodes, None)?;
+                let depth = seen[side][&p.grid].1 + 1;
+                for dir in Move::ALL {
+                    let mut child = p.clone();
+                    if child.perform_move(dir).is_err() || seen[side].contains_key(&child.grid) {
+                        continue;
+                    }
+                    seen[side].insert(child.grid.clone(), (Some(dir), depth));
+                    if let Some(&(_, other)) = seen[1 - side].get(&child.grid)
+                        && depth + other < best
+                    {
+                        best = depth + other;
+                        meet = Some(child.clone());
+                    }
+                    next.push(child);
+                }
+            }
+            frontiers[side] = next;
+        }
+        let meet = meet.unwrap_or_else(|| self.clone());
+        let mut moves = trace(&seen[0], &meet);
+        let back = trace(&seen[1], &meet);
+        moves.extend(back.iter().rev().map(|dir| dir.inverse()));
+        Ok(moves)
+    }
+}
+
+// moves from the root of a search side to p, following the moves it stored
+fn trace(seen: &HashMap<Vec<u32>, (Option<Move>, u32)>, p: &Puzzle) -> Vec<Move> {
+    let mut cur = p.clone();
+    let mut moves = Vec::new();
+    while let Some(&(Some(dir), _)) = seen.get(&cur.grid) {
+        moves.push(dir);
+        if cur.perform_move(dir.inverse()).is_err() {
+            break;
+        }
+    }
+    moves.reverse();
+    moves
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rank_roundtrip() {
+        for r in 0..factorial(5) {
+            assert_eq!(rank(&unrank(r, 5)), r);
+        }
+        assert_eq!(rank(&[0, 1, 2, 3]), 0);
+        assert_eq!(rank(&[3, 2, 1, 0]), factorial(4) - 1);
+    }
+
+    #[test]
+    fn test_2x3_table() {
+        let table = DistanceTable::build(2, 3).unwrap();
+        let reachable = table.distances().iter().filter(|&&d| d != UNREACHABLE);
+        assert_eq!(reachable.count(), factorial(6) / 2);
+        let mut p = Puzzle::new(vec![vec![4, 3, 2], vec![1, 0, 5]]).unwrap();
+        let d = table.distance(&p).unwrap();
+        let moves = table.solve(&p).unwrap();
+        assert_eq!(moves.len(), d as usize);
+        for dir in moves {
+            p.perform_move(dir).unwrap();
+        }
+        assert!(p.is_solved());
+        assert_eq!(table.next_move(&p), None);
+        // never longer than the reduction method
+        let mut p = Puzzle::new(vec![vec![4, 3, 2], vec![1, 0, 5]]).unwrap();
+        assert!(p.solve().unwrap().len() >= d as usize);
+    }
+
+    #[test]
+    fn test_path_between() {
+        let table = DistanceTable::build(2, 3).unwrap();
+        let goal = Puzzle::goal(2, 3).unwrap();
+        for seed in 0..20 {
+            let a = Puzzle::scrambled(2, 3, Scramble::Uniform, seed).unwrap();
+            let b = Puzzle::scrambled(2, 3, Scramble::Uniform, seed + 100).unwrap();
+            // to the goal it agrees with the table
+            let home = a.path_between(&goal).unwrap();
+            assert_eq!(Some(home.len() as u8), table.distance(&a));
+            let moves = a.path_between(&b).unwrap();
+            let mut replay = a.clone();
+            for &dir in &moves {
+                replay.perform_move(dir).unwrap();
+            }
+            assert_eq!(replay.tiles(), b.tiles());
+            assert_eq!(b.path_between(&a).unwrap().len(), moves.len());
+            assert!(moves.len() <= home.len() + b.path_between(&goal).unwrap().len());
+        }
+        let swapped = Puzzle::new(vec![vec![2, 1], vec![3, 0]]).unwrap();
+        let goal = Puzzle::goal(2, 2).unwrap();
+        assert_eq!(swapped.path_between(&goal), Err(SolveError::Unsolvable));
+        assert_eq!(goal.path_between(&goal), Ok(vec![]));
+        let line = Puzzle::goal(1, 4).unwrap();
+        assert_eq!(goal.path_between(&line), Err(SolveError::Unsolvable));
+    }
+
+    #[test]
+    fn test_3x3_table() {
+        let table = DistanceTable::build(3, 3).unwrap();
+        let max = table
+            .distances()
+            .iter()
+            .filter(|&&d| d != UNREACHABLE)
+            .max();
+        assert_eq!(max, Some(&31));
+        let p = Puzzle::new(vec![vec![1, 8, 0], vec![2, 3, 7], vec![5, 4, 6]]).unwrap();
+        let d = table.distance(&p).unwrap();
+        let dir = table.next_move(&p).unwrap();
+        let mut next = p.clone();
+        next.perform_move(dir).unwrap();
+        assert_eq!(table.distance(&next), Some(d - 1));
+    }
+
+    #[test]
+    fn test_unsolvable_and_wrong_size() {
+        let table = DistanceTable::build(2, 2).unwrap();
+        let p = Puzzle::new(vec![vec![2, 1], vec![3, 0]]).unwrap();
+        assert_eq!(table.distance(&p), None);
+        let p = Puzzle::new(vec![vec![1, 2, 0]]).unwrap();
+        assert_eq!(table.distance(&p), None);
+        assert!(matches!(
+            DistanceTable::build(3, 4),
+            Err(TableError::TooLarge { cells: 12 })
+        ));
+    }
+
+    #[test]
+    fn test_save_load() {
+        let table = DistanceTable::build(3, 2).unwrap();
+        let path = std::env::temp_dir().join(format!("puzzle_15_table_{}.bin", std::process::id()));
+        table.save(&path).unwrap();
+        let loaded = DistanceTable::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, table);
+        assert!(matches!(
+            DistanceTable::read_from(&b"nope"[..]),
+            Err(TableError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,167 @@
+/*
+ * torus.rs
+ * Wrap-around boards
+ * solvability
+ *   - a line keeps the cyclic order of its tiles
+ *   - both sides even: the board is still two-coloured, same rule as flat
+ *   - an odd side: the blank can go round it and flip the parity, all solvable
+ * solving
+ *   - solve() is a parity fix-up, not a torus solver: laps of the blank round
+ *     an odd row / column (or round a line) until the flat rule holds, then
+ *     the reduction solver on the flat board; it never wraps to save moves,
+ *     so its solutions are the flat ones plus at most the laps
+ *   - the optimal and weighted searches use wrap moves directly, they are
+ *     the ones that find shorter solutions through the edges
+ */
+
+use crate::puzzle::*;
+
+impl Puzzle {
+    // Same board with another edge topology
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub(crate) fn is_solvable_torus(&self) -> bool {
+        if self.rows == 1 || self.cols == 1 {
+            // tiles read round the ring must count up from wherever 1 is
+            let tiles: Vec<u32> = self.grid.iter().copied().filter(|&t| t != 0).collect();
+            let start = tiles.iter().position(|&t| t == 1).unwrap_or(0);
+            return (0..tiles.len()).all(|i| tiles[(start + i) % tiles.len()] == i as u32 + 1);
+        }
+        if self.rows.is_multiple_of(2) && self.cols.is_multiple_of(2) {
+            return self.is_solvable_flat();
+        }
+        true
+    }
+
+    // Wrap laps until the flat solver can finish, counted as solver moves
+    pub(crate) fn lap_to_flat_parity(&mut self) -> Result<(), SolveError> {
+        let (rows, cols) = (self.rows, self.cols);
+        // a lap along a line shifts every tile one place round the ring
+        let lap = if cols > 1 && (rows == 1 || cols % 2 == 1) {
+            (Move::Right, cols)
+        } else if rows > 1 && (cols == 1 || rows % 2 == 1) {
+            (Move::Down, rows)
+        } else {
+            // even by even, the flat rule already holds
+            return Ok(());
+        };
+        for _ in 0..rows * cols {
+            if self.is_solvable_flat() {
+                return Ok(());
+            }
+            for _ in 0..lap.1 {
+                self.step(lap.0)?;
+            }
+        }
+        Err(self.violation("wrap laps did not reach a flat solvable board"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    // every board reachable from the goal by wrap moves
+    fn reachable(rows: usize, cols: usize) -> HashSet<Vec<u32>> {
+        let goal = Puzzle::goal(rows, cols)
+            .unwrap()
+            .with_topology(Topology::Torus);
+        let mut seen = HashSet::from([goal.tiles().to_vec()]);
+        let mut stack = vec![goal];
+        while let Some(p) = stack.pop() {
+            for dir in Move::ALL {
+                let mut next = p.clone();
+                if next.perform_move(dir).is_ok() && seen.insert(next.tiles().to_vec()) {
+                    stack.push(next);
+                }
+            }
+        }
+        seen
+    }
+
+    #[test]
+    fn test_solvability_rule() {
+        for (rows, cols) in [(1, 4), (3, 1), (2, 2), (2, 3), (3, 2), (2, 4), (3, 3)] {
+            let seen = reachable(rows, cols);
+            let cells = rows * cols;
+            for r in 0..crate::puzzle::tables::factorial(cells) {
+                let tiles = crate::puzzle::tables::unrank(r, cells);
+                let p = Puzzle::from_tiles(rows, cols, tiles)
+                    .unwrap()
+                    .with_topology(Topology::Torus);
+                assert_eq!(p.is_solvable(), seen.contains(p.tiles()), "{:?}", p.tiles());
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrap_moves() {
+        let mut p = Puzzle::goal(3, 3).unwrap().with_topology(Topology::Torus);
+        p.perform_move(Move::Right).unwrap();
+        assert_eq!(p.tiles(), &[1, 2, 3, 4, 5, 6, 0, 8, 7]);
+        assert_eq!(p.solve_optimal().unwrap(), vec![Move::Left]);
+        // flat rule says unsolvable, one wrap lap fixes it
+        let p = Puzzle::new(vec![vec![2, 1, 3], vec![4, 5, 6], vec![7, 8, 0]]).unwrap();
+        assert!(!p.is_solvable());
+        let torus = p.with_topology(Topology::Torus);
+        let moves = torus.clone().solve().unwrap();
+        let mut replay = torus.clone();
+        for dir in &moves {
+            replay.perform_move(*dir).unwrap();
+        }
+        assert!(replay.is_solved());
+        let optimal = torus.solve_optimal().unwrap();
+        assert!(optimal.len() <= moves.len());
+        // a ring only rotates
+        let mut ring = Puzzle::new(vec![vec![3, 0, 1, 2]])
+            .unwrap()
+            .with_topology(Topology::Torus);
+        assert!(ring.is_solvable());
+        assert!(ring.solve().is_ok() && ring.is_solved());
+    }
+
+    #[test]
+    fn test_solves_torus_boards() {
+        for (rows, cols) in [(3, 4), (4, 4), (5, 3), (4, 6)] {
+            for seed in 0..5 {
+                let flat = Puzzle::scrambled(rows, cols, Scramble::Uniform, seed).unwrap();
+                // swap two tiles: unsolvable flat, solvable unless both sides are even
+                let mut tiles = flat.tiles().to_vec();
+                let pair: Vec<usize> = (0..tiles.len())
+                    .filter(|&i| tiles[i] != 0)
+                    .take(2)
+                    .collect();
+                tiles.swap(pair[0], pair[1]);
+                let p = Puzzle::from_tiles(rows, cols, tiles)
+                    .unwrap()
+                    .with_topology(Topology::Torus);
+                // a board the flat rule allows gets no laps, only the flat solution
+                let mut torus = flat.clone().with_topology(Topology::Torus);
+                let flat_moves = flat.clone().solve().unwrap();
+                assert!(torus.solve().unwrap().len() <= flat_moves.len());
+                let even = rows % 2 == 0 && cols % 2 == 0;
+                assert_eq!(p.is_solvable(), !even);
+                let mut solved = p.clone();
+                match solved.solve() {
+                    Ok(moves) => {
+                        let mut replay = p.clone();
+                        for dir in moves {
+                            replay.perform_move(dir).unwrap();
+                        }
+                        assert!(replay.is_solved() && solved.is_solved());
+                        assert_eq!(solved.topology(), Topology::Torus);
+                    }
+                    Err(err) => assert!(even && err == SolveError::Unsolvable),
+                }
+            }
+        }
+    }
+}
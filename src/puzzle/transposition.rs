@@ -0,0 +1,281 @@
+/*
+ * transposition.rs
+ * Transposition table for the optimal solver
+ *   - boards are keyed by their Zobrist hash, which Puzzle keeps up to date
+ *   - an entry holds a lower bound on the distance to the goal, valid for the
+ *     rest of the solve that stored it (the search below a board skips the move
+ *     back to its parent, so the bound only holds for paths from that root),
+ *     and the depth the board was last searched at, valid for one iteration
+ *   - fixed number of slots chosen from a memory budget, one entry per slot
+ */
+
+use std::mem::size_of;
+
+use crate::puzzle::ida::Outcome;
+use crate::puzzle::random::Rng;
+use crate::puzzle::*;
+
+// random key of a tile sitting on a cell, derived on the fly instead of stored
+pub(crate) fn zobrist_key(idx: usize, tile: u32) -> u64 {
+    Rng::new(((idx as u64) << 32) | tile as u64).next_u64()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Entry {
+    key: u64,       // 0 marks an empty slot
+    lower: u32,     // distance to the goal is at least this, within `solve`
+    g: u32,         // depth of the last visit
+    iteration: u32, // iteration of the last visit
+    solve: u32,     // solve of the last visit
+}
+
+/* Fixed size table, replaces stale entries first, then deeper ones */
+#[derive(Debug, Clone)]
+pub struct TranspositionTable {
+    entries: Vec<Entry>,
+    iteration: u32,
+    solve: u32,
+    hits: u64,
+}
+
+impl TranspositionTable {
+    // As many slots as fit in `bytes`, rounded down to a power of two
+    pub fn new(bytes: usize) -> Self {
+        let slots = bytes / size_of::<Entry>();
+        let slots = match slots {
+            0 => 0,
+            n => 1 << n.ilog2(),
+        };
+        Self {
+            entries: vec![Entry::default(); slots],
+            iteration: 0,
+            solve: 0,
+            hits: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    // lookups that found their board
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    // forget everything, e.g. to give the memory back between solves
+    pub fn clear(&mut self) {
+        self.entries.fill(Entry::default());
+        self.hits = 0;
+    }
+
+    fn slot(&self, key: u64) -> Option<usize> {
+        match self.entries.len() {
+            0 => None,
+            n => Some(key as usize & (n - 1)),
+        }
+    }
+
+    fn probe(&mut self, key: u64) -> Option<Entry> {
+        let entry = self.entries[self.slot(key)?];
+        if entry.key != key {
+            return None;
+        }
+        self.hits += 1;
+        Some(entry)
+    }
+
+    fn store(&mut self, entry: Entry) {
+        let Some(slot) = self.slot(entry.key) else {
+            return;
+        };
+        let old = &mut self.entries[slot];
+        // a board searched nearer the root this iteration covers a larger subtree
+        if old.key == entry.key || old.iteration != entry.iteration || old.g >= entry.g {
+            *old = entry;
+        }
+    }
+}
+
+impl Puzzle {
+    // Shortest solution, pruning boards the table has already seen
+    pub fn solve_optimal_tt(
+        &self,
+        tt: &mut TranspositionTable,
+    ) -> Result<SearchReport, SolveError> {
+        self.solve_optimal_tt_with(tt, &mut SolveOptions::default())
+    }
+
+    pub fn solve_optimal_tt_with(
+        &self,
+        tt: &mut TranspositionTable,
+        opts: &mut SolveOptions,
+    ) -> Result<SearchReport, SolveError> {
+        if !self.is_solvable() {
+            return Err(SolveError::Unsolvable);
+        }
+        // lower bounds from earlier solves no longer hold
+        tt.solve = tt.solve.wrapping_add(1);
+        let mut search = TtIda {
+            puzzle: self.clone(),
+            path: Vec::new(),
+            nodes: 0,
+            tt,
+        };
+        let md = search.puzzle.manhattan();
+        let mut bound = md;
+        loop {
+            opts.checkpoint(Progress::nodes(search.nodes, Some(bound)))?;
+            search.tt.iteration = search.tt.iteration.wrapping_add(1);
+            match search.search(0, md, bound, None, opts)? {
+                Outcome::Found => {
+                    return Ok(SearchReport {
+                        moves: search.path,
+                        expanded: search.nodes,
+                        bound: Some(1.0),
+                    });
+                }
+                Outcome::Exceeded(next) => bound = next,
+            }
+        }
+    }
+}
+
+/* IDA* with Manhattan distance raised by the table's lower bounds */
+struct TtIda<'a> {
+    puzzle: Puzzle,
+    path: Vec<Move>,
+    nodes: u64,
+    tt: &'a mut TranspositionTable,
+}
+
+impl TtIda<'_> {
+    fn search(
+        &mut self,
+        g: u32,
+        md: u32,
+        bound: u32,
+        last: Option<Move>,
+        opts: &mut SolveOptions,
+    ) -> Result<Outcome, SolveError> {
+        self.nodes += 1;
+        opts.tick(self.nodes, Some(bound))?;
+        let key = self.puzzle.zobrist;
+        let mut h = md;
+        if let Some(entry) = self.tt.probe(key) {
+            if entry.solve == self.tt.solve {
+                h = h.max(entry.lower);
+            }
+            // already searched this iteration with at least as much budget left;
+            // distances share the parity of the Manhattan distance, so every f
+            // past the bound is at least bound + 2
+            if entry.iteration == self.tt.iteration && entry.g <= g {
+                return Ok(Outcome::Exceeded(bound + 2));
+            }
+        }
+        if g + h > bound {
+            return Ok(Outcome::Exceeded(g + h));
+        }
+        if md == 0 {
+            return Ok(Outcome::Found);
+        }
+        let mut min = u32::MAX;
+        for dir in Move::ALL {
+            if Some(dir.inverse()) == last || !self.puzzle.is_legal(dir) {
+                continue;
+            }
+            let next_md = (md as i32 + self.puzzle.manhattan_delta(dir)) as u32;
+            if self.puzzle.perform_move(dir).is_err() {
+                continue;
+            }
+            self.path.push(dir);
+            match self.search(g + 1, next_md, bound, Some(dir), opts)? {
+                Outcome::Found => return Ok(Outcome::Found),
+                Outcome::Exceeded(f) => min = min.min(f),
+            }
+            self.path.pop();
+            let _ = self.puzzle.perform_move(dir.inverse());
+        }
+        // nothing within the bound below this board
+        let lower = if min == u32::MAX { h } else { h.max(min - g) };
+        self.tt.store(Entry {
+            key,
+            lower,
+            g,
+            iteration: self.tt.iteration,
+            solve: self.tt.solve,
+        });
+        Ok(Outcome::Exceeded(min))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_incremental_hash() {
+        let mut p = Puzzle::scrambled(4, 4, Scramble::Uniform, 5).unwrap();
+        let start = p.zobrist();
+        for dir in [Move::Up, Move::Left, Move::Down, Move::Right] {
+            if p.perform_move(dir).is_ok() {
+                let fresh = Puzzle::from_tiles(4, 4, p.tiles().to_vec()).unwrap();
+                assert_eq!(p.zobrist(), fresh.zobrist());
+            }
+        }
+        assert_ne!(p.zobrist(), start);
+        let goal = Puzzle::goal(4, 4).unwrap();
+        assert_eq!(goal.zobrist(), Puzzle::goal(4, 4).unwrap().zobrist());
+    }
+
+    #[test]
+    fn test_optimal_with_table() {
+        let table = DistanceTable::build(3, 3).unwrap();
+        let mut tt = TranspositionTable::new(1 << 16);
+        for seed in 0..30 {
+            let p = Puzzle::scrambled(3, 3, Scramble::Uniform, seed).unwrap();
+            let report = p.solve_optimal_tt(&mut tt).unwrap();
+            assert_eq!(Some(report.length() as u8), table.distance(&p));
+            let mut replay = p.clone();
+            for dir in report.moves {
+                replay.perform_move(dir).unwrap();
+            }
+            assert!(replay.is_solved());
+        }
+        assert!(tt.hits() > 0);
+        // a table with a handful of slots still gives optimal answers
+        let mut tiny = TranspositionTable::new(64);
+        assert_eq!(tiny.capacity(), 2);
+        let p = Puzzle::scrambled(3, 3, Scramble::Uniform, 9).unwrap();
+        let report = p.solve_optimal_tt(&mut tiny).unwrap();
+        assert_eq!(Some(report.length() as u8), table.distance(&p));
+    }
+
+    #[test]
+    fn test_table_reused_across_solves() {
+        // bounds from one solve must not leak into the next
+        let table = DistanceTable::build(3, 3).unwrap();
+        let mut tt = TranspositionTable::new(1 << 20);
+        for seed in 0..300 {
+            let p = Puzzle::scrambled(3, 3, Scramble::Uniform, seed).unwrap();
+            let report = p.solve_optimal_tt(&mut tt).unwrap();
+            assert_eq!(
+                Some(report.length() as u8),
+                table.distance(&p),
+                "seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn test_fewer_nodes() {
+        let p = Puzzle::scrambled(4, 4, Scramble::Walk(80), 4).unwrap();
+        let plain = p.solve_optimal_tt(&mut TranspositionTable::new(0)).unwrap();
+        let mut tt = TranspositionTable::new(1 << 20);
+        let cached = p.solve_optimal_tt(&mut tt).unwrap();
+        assert_eq!(plain.length(), p.solve_optimal().unwrap().len());
+        assert_eq!(cached.length(), plain.length());
+        assert!(cached.expanded < plain.expanded);
+    }
+}
@@ -6,34 +6,132 @@
  */
 
 use crate::puzzle::Move;
+use crate::puzzle::Phase;
 use crate::puzzle::Puzzle;
+use crate::puzzle::PuzzleError;
+use crate::puzzle::Snapshot;
+use crate::puzzle::Topology;
+use crate::puzzle::WALL;
+use crate::puzzle::transposition::zobrist_key;
+
+impl Move {
+    pub const ALL: [Move; 4] = [Move::Up, Move::Down, Move::Left, Move::Right];
+    // ALL plus the moves between the layers of a Cube
+    pub const SPATIAL: [Move; 6] = [
+        Move::Up,
+        Move::Down,
+        Move::Left,
+        Move::Right,
+        Move::Forward,
+        Move::Back,
+    ];
+
+    // move that undoes this one
+    pub fn inverse(self) -> Move {
+        match self {
+            Move::Up => Move::Down,
+            Move::Down => Move::Up,
+            Move::Left => Move::Right,
+            Move::Right => Move::Left,
+            Move::Forward => Move::Back,
+            Move::Back => Move::Forward,
+        }
+    }
+}
 
 impl Puzzle {
     // Initial state constructor
-    pub fn new(in_grid: Vec<Vec<u32>>) -> Self {
+    pub fn new(in_grid: Vec<Vec<u32>>) -> Result<Self, PuzzleError> {
         let rows = in_grid.len();
-        let cols = in_grid[0].len();
-        let mut blank = (rows, cols);
-        let mut grid = vec![0; rows * cols];
-        for r in 0..rows {
-            for c in 0..cols {
-                grid[r * cols + c] = in_grid[r][c];
-                if in_grid[r][c] == 0 {
-                    blank = (r, c);
+        let cols = in_grid.first().map_or(0, |row| row.len());
+        for (r, row) in in_grid.iter().enumerate() {
+            if row.len() != cols {
+                return Err(PuzzleError::RaggedRow {
+                    row: r,
+                    len: row.len(),
+                    expected: cols,
+                });
+            }
+        }
+        Self::from_tiles(rows, cols, in_grid.concat())
+    }
+
+    // Constructor from tiles in row-major order
+    // WALL marks a cell no tile may enter, tile t belongs in cell t - 1
+    pub fn from_tiles(rows: usize, cols: usize, tiles: Vec<u32>) -> Result<Self, PuzzleError> {
+        if rows == 0 || cols == 0 {
+            return Err(PuzzleError::EmptyBoard);
+        }
+        if tiles.len() != rows * cols {
+            return Err(PuzzleError::InvalidTiles);
+        }
+        // the blank belongs in the last free cell
+        let Some(blank_goal) = tiles.iter().rposition(|&t| t != WALL) else {
+            return Err(PuzzleError::InvalidTiles);
+        };
+        let mut blank = None;
+        let mut seen = vec![false; rows * cols];
+        for (i, &tile) in tiles.iter().enumerate() {
+            if tile == WALL {
+                continue;
+            }
+            if tile == 0 {
+                if blank.is_some() {
+                    return Err(PuzzleError::InvalidTiles);
                 }
+                blank = Some((i / cols, i % cols));
+                continue;
+            }
+            let goal = tile as usize - 1;
+            if goal >= tiles.len() || goal == blank_goal || tiles[goal] == WALL || seen[goal] {
+                return Err(PuzzleError::InvalidTiles);
             }
+            seen[goal] = true;
         }
-        Self {
-            grid,
+        let Some(blank) = blank else {
+            return Err(PuzzleError::InvalidTiles);
+        };
+        let walled = tiles.contains(&WALL);
+        let zobrist = tiles
+            .iter()
+            .enumerate()
+            .fold(0, |hash, (idx, &tile)| hash ^ zobrist_key(idx, tile));
+        Ok(Self {
+            grid: tiles,
             blank,
             rows,
             cols,
+            topology: Topology::Flat,
+            walled,
             row_offset: 0,
             col_offset: 0,
-        }
+            budget: usize::MAX,
+            solution: Vec::new(),
+            phase: Phase::TopLeftCorner,
+            banished: 0,
+            zobrist,
+        })
     }
 
-    fn index(&self, row: usize, col: usize) -> usize {
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    // tiles in row-major order, 0 is the blank, WALL a wall
+    pub fn tiles(&self) -> &[u32] {
+        &self.grid
+    }
+
+    // Zobrist hash of the tiles, equal boards of one size hash alike
+    pub fn zobrist(&self) -> u64 {
+        self.zobrist
+    }
+
+    pub(crate) fn index(&self, row: usize, col: usize) -> usize {
         row * self.cols + col
     }
 
@@ -48,6 +146,7 @@ impl Puzzle {
 
     pub(crate) fn set(&mut self, row: usize, col: usize, val: u32) {
         let idx = self.index(row + self.row_offset, col + self.col_offset);
+        self.zobrist ^= zobrist_key(idx, self.grid[idx]) ^ zobrist_key(idx, val);
         self.grid[idx] = val;
     }
     // get row of blank piece
@@ -67,7 +166,7 @@ impl Puzzle {
     }
 
     // get inversions
-    fn count_inversions(&self) -> usize {
+    pub(crate) fn count_inversions(&self) -> usize {
         // O(n^2) brute force should work well as grids are generally small
         // also called only once to check solvability
         let mut inversions = 0;
@@ -84,41 +183,139 @@ impl Puzzle {
 
     // Check Solvability
     pub fn is_solvable(&self) -> bool {
-        if self.cols % 2 == 0 {
+        if self.walled {
+            return self.is_solvable_walled();
+        }
+        match self.topology {
+            Topology::Flat => self.is_solvable_flat(),
+            Topology::Torus => self.is_solvable_torus(),
+        }
+    }
+
+    // solvable without wrapping around the edges
+    pub(crate) fn is_solvable_flat(&self) -> bool {
+        if self.rows == 1 || self.cols == 1 {
+            // a line can only shift the blank, tiles keep their order
+            return self.count_inversions() == 0;
+        }
+        if self.cols.is_multiple_of(2) {
             (self.count_inversions() + self.blank.0) % 2 == (self.rows - 1) % 2
         } else {
-            self.count_inversions() % 2 == 0
+            self.count_inversions().is_multiple_of(2)
         }
     }
 
-    // Move
-    pub fn perform_move(&mut self, dir: Move) {
-        // TODO: check for illegal moves
-        let (cur_blank_row, cur_blank_col) = self.blank;
+    // Check if the blank can move in the given direction (within the active window)
+    pub fn is_legal(&self, dir: Move) -> bool {
+        let Some((row, col)) = self.neighbour(self.blank, dir) else {
+            return false;
+        };
+        // walls only exist on whole boards, never inside a reduction window
+        !self.walled || self.grid[self.index(row, col)] != WALL
+    }
+
+    // whether (row, col) has a neighbour in dir, walls aside
+    pub(crate) fn in_bounds(&self, (row, col): (usize, usize), dir: Move) -> bool {
+        if self.topology == Topology::Torus {
+            // wrapping needs somewhere else to go
+            return match dir {
+                Move::Up | Move::Down => self.rows > 1,
+                Move::Left | Move::Right => self.cols > 1,
+                Move::Forward | Move::Back => false,
+            };
+        }
         match dir {
-            Move::Down => {
-                self.blank = (cur_blank_row + 1, cur_blank_col);
-            }
-            Move::Left => {
-                self.blank = (cur_blank_row, cur_blank_col - 1);
-            }
-            Move::Right => {
-                self.blank = (cur_blank_row, cur_blank_col + 1);
-            }
-            Move::Up => {
-                self.blank = (cur_blank_row - 1, cur_blank_col);
-            }
+            Move::Up => row > 0,
+            Move::Down => row + 1 < self.rows - self.row_offset,
+            Move::Left => col > 0,
+            Move::Right => col + 1 < self.cols - self.col_offset,
+            // a Puzzle has a single layer
+            Move::Forward | Move::Back => false,
         }
+    }
+
+    // Move
+    pub fn perform_move(&mut self, dir: Move) -> Result<(), PuzzleError> {
+        if !self.is_legal(dir) {
+            return Err(PuzzleError::IllegalMove(dir));
+        }
+        let Some(next) = self.neighbour(self.blank, dir) else {
+            return Err(PuzzleError::IllegalMove(dir));
+        };
+        let (cur_blank_row, cur_blank_col) = self.blank;
+        self.blank = next;
         self.set(
             cur_blank_row,
             cur_blank_col,
             self.get(self.blank.0, self.blank.1),
         );
         self.set(self.blank.0, self.blank.1, 0);
+        Ok(())
+    }
+
+    // cell next to (row, col) in dir, wrapping on a torus; None off the
+    // window and for moves between layers, walls aside
+    pub(crate) fn neighbour(
+        &self,
+        (row, col): (usize, usize),
+        dir: Move,
+    ) -> Option<(usize, usize)> {
+        if !self.in_bounds((row, col), dir) {
+            return None;
+        }
+        let (rows, cols) = (self.rows, self.cols);
+        match (dir, self.topology) {
+            (Move::Up, Topology::Torus) => Some(((row + rows - 1) % rows, col)),
+            (Move::Down, Topology::Torus) => Some(((row + 1) % rows, col)),
+            (Move::Left, Topology::Torus) => Some((row, (col + cols - 1) % cols)),
+            (Move::Right, Topology::Torus) => Some((row, (col + 1) % cols)),
+            (Move::Up, Topology::Flat) => Some((row - 1, col)),
+            (Move::Down, Topology::Flat) => Some((row + 1, col)),
+            (Move::Left, Topology::Flat) => Some((row, col - 1)),
+            (Move::Right, Topology::Flat) => Some((row, col + 1)),
+            (Move::Forward | Move::Back, _) => None,
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            grid: self.grid.clone(),
+            rows: self.rows,
+            cols: self.cols,
+            blank: self.blank,
+            row_offset: self.row_offset,
+            col_offset: self.col_offset,
+        }
     }
 
     // Check if puzzle is solved
     pub fn is_solved(&self) -> bool {
+        if self.walled {
+            return self.is_solved_walled();
+        }
         self.count_inversions() == 0 && self.grid.last() == Some(&0)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_no_layer_moves() {
+        // a flat board never moves between layers, on either topology
+        for topology in [Topology::Flat, Topology::Torus] {
+            let mut p = Puzzle::goal(3, 3).unwrap().with_topology(topology);
+            for dir in [Move::Forward, Move::Back] {
+                assert!(!p.is_legal(dir));
+                assert_eq!(p.neighbour(p.blank, dir), None);
+                assert_eq!(p.perform_move(dir), Err(PuzzleError::IllegalMove(dir)));
+            }
+        }
+        // nor off its edges
+        let p = Puzzle::goal(3, 3).unwrap();
+        assert_eq!(p.neighbour((2, 2), Move::Down), None);
+        assert_eq!(p.neighbour((0, 0), Move::Up), None);
+        assert_eq!(p.neighbour((1, 1), Move::Left), Some((1, 0)));
+    }
+}
@@ -0,0 +1,225 @@
+/*
+ * walls.rs
+ * Boards with walls - cells no tile may enter
+ * tile t still belongs in cell t - 1, the blank in the last free cell
+ * solvability from the graph of free cells, see graph.rs
+ * solving: weighted A*, the reduction needs the full rectangle
+ */
+
+use crate::puzzle::*;
+
+// cell no tile may enter
+pub const WALL: u32 = u32::MAX;
+
+impl Puzzle {
+    // Solved board with walls at the given (row, col) cells
+    pub fn with_walls(
+        rows: usize,
+        cols: usize,
+        walls: &[(usize, usize)],
+    ) -> Result<Self, PuzzleError> {
+        if rows == 0 || cols == 0 {
+            return Err(PuzzleError::EmptyBoard);
+        }
+        let mut tiles: Vec<u32> = (1..=(rows * cols) as u32).collect();
+        for &(row, col) in walls {
+            if row >= rows || col >= cols {
+                return Err(PuzzleError::WallOutOfBounds { row, col });
+            }
+            tiles[row * cols + col] = WALL;
+        }
+        if let Some(last) = tiles.iter().rposition(|&t| t != WALL) {
+            tiles[last] = 0;
+        }
+        Self::from_tiles(rows, cols, tiles)
+    }
+
+    pub fn has_walls(&self) -> bool {
+        self.walled
+    }
+
+    // last free cell, where the blank belongs
+    fn blank_goal(&self) -> usize {
+        self.grid.iter().rposition(|&t| t != WALL).unwrap_or(0)
+    }
+
+    pub(crate) fn is_solved_walled(&self) -> bool {
+        let blank_goal = self.blank_goal();
+        self.grid.iter().enumerate().all(|(idx, &tile)| match tile {
+            WALL => true,
+            0 => idx == blank_goal,
+            _ => tile as usize == idx + 1,
+        })
+    }
+
+    // free cells one move away from idx, each once
+    pub(crate) fn free_neighbours(&self, idx: usize) -> Vec<usize> {
+        let cell = (idx / self.cols, idx % self.cols);
+        let mut out = Vec::new();
+        for dir in Move::ALL {
+            let Some((row, col)) = self.neighbour(cell, dir) else {
+                continue;
+            };
+            let next = self.index(row, col);
+            if next != idx && self.grid[next] != WALL && !out.contains(&next) {
+                out.push(next);
+            }
+        }
+        out
+    }
+
+    // the free cells as a graph decide
+    pub(crate) fn is_solvable_walled(&self) -> bool {
+        self.to_graph().is_solvable()
+    }
+
+    // Weighted A* on the whole board, its moves played like the reduction's;
+    // boards that cannot finish within the budget are never expanded
+    pub(crate) fn solve_walled(
+        &mut self,
+        budget: usize,
+        opts: &mut SolveOptions,
+    ) -> Result<Vec<Move>, SolveError> {
+        let moves = self
+            .solve_weighted_within(&WeightedConfig::default(), budget, opts)?
+            .moves;
+        for &dir in &moves {
+            if self.perform_move(dir).is_err() {
+                return Err(self.violation("search move ran into a wall"));
+            }
+        }
+        Ok(moves)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::puzzle::tables::{factorial, unrank};
+    use std::collections::{HashSet, VecDeque};
+
+    // every placement of the pieces on the free cells of a solved board
+    fn arrangements(goal: &Puzzle) -> Vec<Puzzle> {
+        let free: Vec<usize> = (0..goal.grid.len())
+            .filter(|&i| goal.grid[i] != WALL)
+            .collect();
+        (0..factorial(free.len()))
+            .map(|r| {
+                let mut tiles = goal.grid.clone();
+                for (i, p) in unrank(r, free.len()).into_iter().enumerate() {
+                    tiles[free[i]] = goal.grid[free[p as usize]];
+                }
+                let p = Puzzle::from_tiles(goal.rows, goal.cols, tiles).unwrap();
+                p.with_topology(goal.topology)
+            })
+            .collect()
+    }
+
+    fn reachable(goal: &Puzzle) -> HashSet<Vec<u32>> {
+        let mut seen = HashSet::from([goal.grid.clone()]);
+        let mut queue = VecDeque::from([goal.clone()]);
+        while let Some(p) = queue.pop_front() {
+            for dir in Move::ALL {
+                let mut next = p.clone();
+                if next.perform_move(dir).is_ok() && seen.insert(next.grid.clone()) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        seen
+    }
+
+    #[test]
+    fn test_walls_block_moves() {
+        let mut p = Puzzle::with_walls(3, 3, &[(1, 1)]).unwrap();
+        assert_eq!(p.tiles(), &[1, 2, 3, 4, WALL, 6, 7, 8, 0]);
+        assert!(p.is_solved() && p.has_walls());
+        p.perform_move(Move::Left).unwrap();
+        assert_eq!(
+            p.perform_move(Move::Up),
+            Err(PuzzleError::IllegalMove(Move::Up))
+        );
+        // the blank goes home to the last free cell, not the last cell
+        let p = Puzzle::with_walls(2, 3, &[(1, 2)]).unwrap();
+        assert_eq!(p.tiles(), &[1, 2, 3, 4, 0, WALL]);
+        assert_eq!(
+            Puzzle::with_walls(2, 2, &[(2, 0)]).unwrap_err(),
+            PuzzleError::WallOutOfBounds { row: 2, col: 0 }
+        );
+        // tile 6 would belong on the wall
+        let tiles = vec![1, 2, 3, 4, 6, WALL, 7, 8, 0];
+        assert_eq!(
+            Puzzle::from_tiles(3, 3, tiles).unwrap_err(),
+            PuzzleError::InvalidTiles
+        );
+        assert_eq!(
+            Puzzle::with_walls(1, 2, &[(0, 0), (0, 1)]).unwrap_err(),
+            PuzzleError::InvalidTiles
+        );
+    }
+
+    #[test]
+    fn test_solvability_rule() {
+        let shapes = [
+            (3, 3, vec![(1, 1)], Topology::Flat),         // cycle
+            (3, 3, vec![(0, 0)], Topology::Flat),         // 2-connected, two-coloured
+            (3, 3, vec![(1, 0), (1, 1)], Topology::Flat), // path
+            (2, 3, vec![(0, 1), (1, 1)], Topology::Flat), // two components
+            (2, 4, vec![(1, 0)], Topology::Flat),         // cut cell
+            (3, 3, vec![(1, 1)], Topology::Torus),        // odd cycles through the wrap
+        ];
+        for (rows, cols, walls, topology) in shapes {
+            let goal = Puzzle::with_walls(rows, cols, &walls)
+                .unwrap()
+                .with_topology(topology);
+            let reachable = reachable(&goal);
+            for p in arrangements(&goal) {
+                assert_eq!(
+                    p.is_solvable(),
+                    reachable.contains(&p.grid),
+                    "{:?} {:?}",
+                    walls,
+                    p.grid
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_solves_walled_boards() {
+        let walls = [(0, 1), (2, 2), (3, 0)];
+        let goal = Puzzle::with_walls(4, 4, &walls).unwrap();
+        for seed in 0..5 {
+            let p = goal.walked(200, seed);
+            assert!(p.is_solvable());
+            let mut solved = p.clone();
+            let moves = solved.solve().unwrap();
+            assert!(solved.is_solved());
+            let mut replay = p.clone();
+            for dir in &moves {
+                replay.perform_move(*dir).unwrap();
+            }
+            assert!(replay.is_solved());
+            assert!(p.solve_optimal().unwrap().len() <= moves.len());
+        }
+        // swapping two tiles breaks the parity
+        let mut tiles = goal.walked(200, 7).tiles().to_vec();
+        let pair: Vec<usize> = (0..tiles.len())
+            .filter(|&i| tiles[i] != 0 && tiles[i] != WALL)
+            .take(2)
+            .collect();
+        tiles.swap(pair[0], pair[1]);
+        let mut p = Puzzle::from_tiles(4, 4, tiles).unwrap();
+        assert_eq!(p.solve(), Err(SolveError::Unsolvable));
+        // a tight budget stops the search early, not after it
+        let mut p = goal.walked(200, 3);
+        let mut opts = SolveOptions {
+            max_nodes: Some(5),
+            ..SolveOptions::default()
+        };
+        assert_eq!(
+            p.solve_walled(2, &mut opts),
+            Err(SolveError::BudgetExceeded { budget: 2 })
+        );
+    }
+}
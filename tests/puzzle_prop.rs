@@ -9,7 +9,7 @@ fn make_grid(tiles: Vec<u32>, rows: usize, cols: usize) -> Vec<Vec<u32>> {
 fn puzzle_stategy(rows: usize, cols: usize) -> impl Strategy<Value = Vec<u32>> {
     proptest::strategy::Just((0..(rows as u32 * cols as u32)).collect::<Vec<_>>()).prop_shuffle()
     // .prop_filter("must be solvable", move |tiles| {
-    //     let p = Puzzle::new(make_grid(tiles.clone(), rows, cols));
+    //     let p = Puzzle::new(make_grid(tiles.clone(), rows, cols)).unwrap();
     //     p.is_solvable()
     // })
 }
@@ -18,30 +18,52 @@ proptest! {
   #![proptest_config(ProptestConfig::with_cases(1000))]
   #[test]
   fn solves_4x4(puzzle in puzzle_stategy(4, 4)) {
-    let mut p = Puzzle::new(make_grid(puzzle.clone(), 4, 4));
+    let mut p = Puzzle::new(make_grid(puzzle.clone(), 4, 4)).unwrap();
     if p.is_solvable() {
-      prop_assert!(p.solve());
+      prop_assert!(p.solve().is_ok());
     }
   }
   #[test]
   fn solves_5x5(puzzle in puzzle_stategy(5, 5)) {
-    let mut p = Puzzle::new(make_grid(puzzle.clone(), 5, 5));
+    let mut p = Puzzle::new(make_grid(puzzle.clone(), 5, 5)).unwrap();
     if p.is_solvable() {
-      prop_assert!(p.solve());
+      prop_assert!(p.solve().is_ok());
     }
   }
   #[test]
   fn solves_3x3(puzzle in puzzle_stategy(3, 3)) {
-    let mut p = Puzzle::new(make_grid(puzzle.clone(), 3, 3));
+    let mut p = Puzzle::new(make_grid(puzzle.clone(), 3, 3)).unwrap();
     // dbg!(&p);
     if p.is_solvable() {
-      prop_assert!(p.solve()); }
+      prop_assert!(p.solve().is_ok()); }
+  }
+  #[test]
+  fn solution_replays_4x4(puzzle in puzzle_stategy(4, 4)) {
+    let mut p = Puzzle::new(make_grid(puzzle.clone(), 4, 4)).unwrap();
+    if let Ok(moves) = p.solve() {
+      let mut replay = Puzzle::new(make_grid(puzzle.clone(), 4, 4)).unwrap();
+      for dir in moves {
+        prop_assert!(replay.perform_move(dir).is_ok());
+      }
+      prop_assert!(replay.is_solved());
+    } else {
+      prop_assert!(!p.is_solvable());
+    }
+  }
+  #[test]
+  fn solves_rectangles((rows, cols, puzzle) in (2usize..6, 2usize..6)
+      .prop_flat_map(|(rows, cols)| (Just(rows), Just(cols), puzzle_stategy(rows, cols)))) {
+    let mut p = Puzzle::new(make_grid(puzzle.clone(), rows, cols)).unwrap();
+    if p.is_solvable() {
+      // debug builds also check the reduction invariants along the way
+      prop_assert_eq!(p.solve().err(), None);
+    }
   }
   #[test]
   fn solves_2x2(puzzle in puzzle_stategy(2, 2)) {
-    let mut p = Puzzle::new(make_grid(puzzle.clone(), 2, 2));
+    let mut p = Puzzle::new(make_grid(puzzle.clone(), 2, 2)).unwrap();
     if p.is_solvable() {
-      prop_assert!(p.solve());
+      prop_assert!(p.solve().is_ok());
     }
   }
 }